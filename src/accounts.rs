@@ -0,0 +1,129 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use tokio::sync::{broadcast, watch};
+use crate::connection::ConnectionState;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::user_account_update::{AccessType, UpdateType};
+use crate::rithmic_proto_objects::rti::{ResponseAccountList, UserAccountUpdate};
+
+/// An account the user can access.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AccountAccess {
+    pub account_id: String,
+    pub account_name: Option<String>,
+    pub fcm_id: Option<String>,
+    pub ib_id: Option<String>,
+    pub account_currency: Option<String>,
+    /// None until a `UserAccountUpdate` reports the access level.
+    pub access_type: Option<AccessType>,
+}
+
+impl AccountAccess {
+    fn new(account_id: String) -> Self {
+        Self {
+            account_id,
+            account_name: None,
+            fcm_id: None,
+            ib_id: None,
+            account_currency: None,
+            access_type: None,
+        }
+    }
+
+    /// Returns false if the account is known to be read only.
+    pub fn can_trade(&self) -> bool {
+        self.access_type != Some(AccessType::ReadOnly)
+    }
+}
+
+/// The accounts the user can access, keyed by account_id.
+/// Filled from `ResponseAccountList` (303) and kept current with `UserAccountUpdate` (76).
+/// Cloning the cache gives another handle to the same accounts.
+#[derive(Clone, Debug, Default)]
+pub struct AccountCache {
+    accounts: Arc<DashMap<String, AccountAccess>>,
+}
+
+impl AccountCache {
+    pub fn get(&self, account_id: &str) -> Option<AccountAccess> {
+        self.accounts.get(account_id).map(|account| account.clone())
+    }
+
+    pub fn accounts(&self) -> Vec<AccountAccess> {
+        self.accounts.iter().map(|account| account.value().clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Add or refresh an account from a row of `ResponseAccountList`, the access level is kept.
+    pub fn apply_account_list(&self, response: &ResponseAccountList) {
+        let account_id = match &response.account_id {
+            Some(account_id) => account_id.clone(),
+            None => return,
+        };
+        let mut account = self.accounts.entry(account_id.clone()).or_insert_with(|| AccountAccess::new(account_id));
+        account.account_name = response.account_name.clone();
+        account.fcm_id = response.fcm_id.clone();
+        account.ib_id = response.ib_id.clone();
+        account.account_currency = response.account_currency.clone();
+    }
+
+    /// Add, update or remove an account when access is granted or revoked.
+    pub fn apply_update(&self, update: &UserAccountUpdate) {
+        let account_id = match &update.account_id {
+            Some(account_id) => account_id.clone(),
+            None => return,
+        };
+        if update.update_type == Some(UpdateType::Remove as i32) {
+            self.accounts.remove(&account_id);
+            return;
+        }
+        let mut account = self.accounts.entry(account_id.clone()).or_insert_with(|| AccountAccess::new(account_id));
+        if update.account_name.is_some() {
+            account.account_name = update.account_name.clone();
+        }
+        if update.fcm_id.is_some() {
+            account.fcm_id = update.fcm_id.clone();
+        }
+        if update.ib_id.is_some() {
+            account.ib_id = update.ib_id.clone();
+        }
+        if let Some(access_type) = update.access_type.and_then(|access_type| AccessType::try_from(access_type).ok()) {
+            account.access_type = Some(access_type);
+        }
+    }
+}
+
+/// Keep the cache current with the messages of a plant connection until the connection ends.
+pub(crate) async fn track_accounts(
+    cache: AccountCache,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    mut state: watch::Receiver<ConnectionState>,
+) {
+    loop {
+        tokio::select! {
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::ResponseAccountList(response)) => cache.apply_account_list(&response),
+                    Ok(RithmicMessage::UserAccountUpdate(update)) => cache.apply_update(&update),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Account cache lagged, skipped {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() || state.borrow().is_terminal() {
+                    return;
+                }
+            }
+        }
+    }
+}
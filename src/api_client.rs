@@ -1,19 +1,42 @@
 use std::collections::{BTreeMap};
 use std::io::{Cursor};
+use std::sync::{Arc, RwLock};
 use dashmap::DashMap;
 use prost::{Message as ProstMessage};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::protocol::Message;
-use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
 use futures_util::{SinkExt, StreamExt};
 use futures_util::stream::{SplitSink};
 use crate::credentials::RithmicCredentials;
 use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
-use crate::rithmic_proto_objects::rti::{RequestLogin, RequestLogout, RequestRithmicSystemInfo, ResponseLogin, ResponseRithmicSystemInfo};
+use crate::rithmic_proto_objects::rti::{RequestLogin, RequestLogout, RequestRithmicSystemGatewayInfo, RequestRithmicSystemInfo, ResponseLogin, ResponseRithmicSystemGatewayInfo, ResponseRithmicSystemInfo};
 use crate::errors::RithmicApiError;
 use prost::encoding::{decode_key, decode_varint, WireType};
-use tokio::sync::RwLock;
 use crate::servers::{server_domains, RithmicServer};
+use crate::systems::{RithmicSystem, RithmicSystemInfo};
+use crate::templates::TemplateId;
+use crate::accounts::{track_accounts, AccountCache};
+use crate::gateways::{fastest, probe_gateway, Gateway, GatewayProbe};
+use crate::response_code::RithmicResponseCode;
+use crate::codec::RithmicCodec;
+use crate::connection::{PlantConnection, Reconnect};
+use crate::config::ClientConfig;
+use crate::heartbeat::DEFAULT_HEARTBEAT_INTERVAL;
+use crate::rate_limit::RateLimiter;
+use crate::request::RithmicRequest;
+use crate::session::LoginSession;
+use crate::timeouts::{connect_websocket, with_timeout};
+use crate::market_by_order::{track_market_by_order, MarketByOrderHandle};
+use crate::price_level_book::{track_price_levels, PriceLevelHandle};
+use crate::quotes::{track_quotes, QuoteCache};
+use crate::subscriptions::{MarketDataGuard, MarketDataSubscriptions};
+use crate::options::{request_option_chain, OptionChain};
+use crate::volume_profile::{track_volume_profile, VolumeProfileHandle};
+use crate::history::{download_time_bars, DownloadProgress, TimeBar, TimeBarDownload};
+use crate::rithmic_proto_objects::rti::request_time_bar_replay::BarType;
+use crate::instruments::{Instrument, InstrumentCache, InstrumentSearch};
+use crate::front_month::{track_front_months, FrontMonth, FrontMonthResolver};
 
 pub const TEMPLATE_VERSION: &str = "5.27";
 
@@ -22,81 +45,143 @@ pub struct RithmicApiClient {
     /// Credentials used for this instance of the api. we can have multiple instances for different brokers.
     credentials: RithmicCredentials,
 
-    pub fcm_id:RwLock<Option<String>>,
+    server_domains: BTreeMap<RithmicServer, String>,
 
-    pub ib_id:RwLock<Option<String>>,
+    /// Frames every outgoing message and validates incoming length headers.
+    codec: RithmicCodec,
 
-    server_domains: BTreeMap<RithmicServer, String>,
+    /// Plants connected with [`RithmicApiClient::connect_plant`].
+    plant_connections: DashMap<SysInfraType, PlantConnection>,
+
+    config: ClientConfig,
+
+    /// Accounts listed or updated on any plant connected with [`RithmicApiClient::connect_plant`].
+    accounts: AccountCache,
+
+    /// Quotes and trades received on the ticker plant.
+    quotes: QuoteCache,
+
+    /// Market data update bits held on the ticker plant.
+    market_data: MarketDataSubscriptions,
+
+    /// Reference data fetched on the ticker plant.
+    instruments: InstrumentCache,
+
+    /// Front month contracts resolved on the ticker plant.
+    front_months: FrontMonthResolver,
+
+    /// Set with [`RithmicApiClient::set_gateway_override`], takes priority over every other gateway.
+    gateway_override: RwLock<Option<String>>,
 
-    pub heartbeat_interval_seconds: DashMap<SysInfraType, u64>
+    /// The gateway chosen by [`RithmicApiClient::select_fastest_gateway`].
+    selected_gateway: RwLock<Option<Gateway>>,
+
+    /// Held while gateways are probed, so parallel logins do not all probe.
+    gateway_selection: tokio::sync::Mutex<()>,
+
+    /// Held by [`RithmicApiClient::connect_plant`] from the session check until the connection is stored, so parallel calls for a plant log in once.
+    plant_logins: DashMap<SysInfraType, Arc<tokio::sync::Mutex<()>>>,
 }
 
 impl RithmicApiClient {
     pub fn new(
         credentials: RithmicCredentials,
         server_domains_toml: String,
+    ) -> Result<Self, RithmicApiError> {
+        RithmicApiClient::with_config(credentials, server_domains_toml, ClientConfig::default())
+    }
+
+    pub fn with_config(
+        credentials: RithmicCredentials,
+        server_domains_toml: String,
+        config: ClientConfig,
     ) -> Result<Self, RithmicApiError> {
         let server_domains = server_domains(server_domains_toml)?;
+        config.validate()?;
+        let instruments = match &config.instrument_file {
+            Some(file) => InstrumentCache::with_file(file)?,
+            None => InstrumentCache::new(),
+        };
         Ok(Self {
             credentials,
-            fcm_id: RwLock::new(None),
-            ib_id: RwLock::new(None),
             server_domains,
-            heartbeat_interval_seconds: DashMap::with_capacity(5),
+            codec: RithmicCodec::new(config.max_frame_size),
+            plant_connections: DashMap::with_capacity(5),
+            accounts: AccountCache::default(),
+            quotes: QuoteCache::default(),
+            market_data: MarketDataSubscriptions::default(),
+            instruments,
+            front_months: FrontMonthResolver::default(),
+            gateway_override: RwLock::new(config.gateway.override_uri.clone()),
+            selected_gateway: RwLock::new(None),
+            gateway_selection: tokio::sync::Mutex::new(()),
+            plant_logins: DashMap::with_capacity(5),
+            config,
         })
     }
 
     /// only used to register and login before splitting the stream.
     async fn send_single_protobuf_message<T: ProstMessage>(
+        &self,
         stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>, message: &T
     ) -> Result<(), RithmicApiError> {
-        let mut buf = Vec::new();
-
-        match message.encode(&mut buf) {
-            Ok(_) => {}
-            Err(e) => return Err(RithmicApiError::ServerErrorDebug(format!("Failed to encode RithmicMessage: {}", e)))
-        }
-
-        let length = buf.len() as u32;
-        let mut prefixed_msg = length.to_be_bytes().to_vec();
-        prefixed_msg.extend(buf);
-        stream.send(Message::Binary(prefixed_msg)).await.map_err(|e| RithmicApiError::WebSocket(e))
+        let prefixed_msg = self.codec.encode(message)?;
+        stream.send(Message::Binary(prefixed_msg)).await.map_err(RithmicApiError::from)
     }
 
     /// Used to receive system and login response before splitting the stream.
     async fn read_single_protobuf_message<T: ProstMessage + Default>(
+        &self,
         stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>
     ) -> Result<T, RithmicApiError> {
-        while let Some(msg) = stream.next().await { //todo change from while to if and test
+        let mut codec = RithmicCodec::new(self.codec.max_frame_size());
+        // loops until one frame is complete, pings and partial frames are skipped, callers bound the wait with a timeout
+        while let Some(msg) = stream.next().await {
             let msg = match msg {
                 Ok(msg) => msg,
                 Err(e) => return Err(RithmicApiError::ServerErrorDebug(format!("Failed to read RithmicMessage: {}", e)))
             };
-            if let Message::Binary(data) = msg {
-                //println!("Received binary data: {:?}", data);
-
-                // Create a cursor for reading the data
-                let mut cursor = Cursor::new(data);
-
-                // Read the 4-byte length header
-                let mut length_buf = [0u8; 4];
-                let _ = tokio::io::AsyncReadExt::read_exact(&mut cursor, &mut length_buf).await.map_err(RithmicApiError::Io);
-                let length = u32::from_be_bytes(length_buf) as usize;
-
-                // Read the Protobuf message
-                let mut message_buf = vec![0u8; length];
-                tokio::io::AsyncReadExt::read_exact(&mut cursor, &mut message_buf).await.map_err(RithmicApiError::Io)?;
-
-                // Decode the Protobuf message
-                return match T::decode(&message_buf[..]) {
-                    Ok(decoded_msg) => Ok(decoded_msg),
-                    Err(e) => Err(RithmicApiError::ProtobufDecode(e)), // Use the ProtobufDecode variant
+            match msg {
+                Message::Binary(data) => {
+                    // The frame may be split over several binary messages, keep reading until it is complete
+                    let mut frames = codec.decode(&data)?;
+                    if frames.is_empty() {
+                        continue;
+                    }
+                    // only one response is expected, anything after it would be lost
+                    if frames.len() > 1 || codec.has_partial_frame() {
+                        return Err(RithmicApiError::InvalidFrame(format!("Expected one response but received {} frames", frames.len() + codec.has_partial_frame() as usize)))
+                    }
+                    let message_buf = frames.remove(0);
+                    // Decode the Protobuf message
+                    return match T::decode(&message_buf[..]) {
+                        Ok(decoded_msg) => Ok(decoded_msg),
+                        Err(e) => Err(RithmicApiError::ProtobufDecode(e)), // Use the ProtobufDecode variant
+                    }
                 }
+                Message::Close(close) => return Err(RithmicApiError::Disconnected(format!("Connection closed before a response was received: {:?}", close))),
+                _ => {}
             }
         }
         Err(RithmicApiError::ServerErrorDebug("No valid message received".to_string()))
     }
 
+    /// Send a request before login and wait for its response, bounded by the login timeout.
+    /// If the timeout fires the stream should be dropped, a late response would otherwise be read as the answer to the next request.
+    async fn send_and_read<T: ProstMessage, R: ProstMessage + Default>(
+        &self,
+        stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+        request: &T,
+        operation: &str,
+    ) -> Result<R, RithmicApiError> {
+        with_timeout(self.config.timeouts.login, || operation.to_string(), async {
+            self.send_single_protobuf_message(stream, request).await?;
+            self.read_single_protobuf_message(stream).await
+        }).await?
+    }
+
+    /// The names of the systems available on the server in the credentials.
+    /// The plant is not needed to list systems, it is only kept so existing callers compile, use [`RithmicApiClient::system_info`] for the structured info.
     pub async fn get_systems(
         &self,
         plant: SysInfraType,
@@ -104,46 +189,143 @@ impl RithmicApiClient {
         if plant as i32 > 5 {
             return Err(RithmicApiError::ClientErrorDebug("Incorrect value for rithmic SysInfraType".to_string()))
         }
-        let domain = match self.server_domains.get(&self.credentials.server_name) {
-            None => return Err(RithmicApiError::ServerErrorDebug(format!("No server domain found, check server.toml for: {:?}", self.credentials.server_name))),
-            Some(domain) => domain
-        };
+        Ok(self.system_info()
+            .await?
+            .into_iter()
+            .map(|info| info.system.to_string())
+            .collect())
+    }
+
+    /// List the systems available on the server in the credentials, use one as the `system_name` of your credentials.
+    pub async fn system_info(
+        &self,
+    ) -> Result<Vec<RithmicSystemInfo>, RithmicApiError> {
+        let domain = self.server_domain()?;
         // establish TCP connection to get the server details
-        let (mut stream, response) = match connect_async(domain).await {
-            Ok((stream, response)) => (stream, response),
-            Err(e) => return Err(RithmicApiError::ServerErrorDebug(format!("Failed to connect to rithmic: {}", e)))
-        };
-        println!("Rithmic connection established: {:?}", response);
+        let mut stream = connect_websocket(domain, &self.config.timeouts).await?;
         // Rithmic System Info Request 16 From Client
         let request = RequestRithmicSystemInfo {
-            template_id: 16,
-            user_msg: vec![format!("{} Signing In", self.credentials.app_name)],
+            template_id: TemplateId::RequestRithmicSystemInfo.id(),
+            user_msg: vec![],
         };
 
-        RithmicApiClient::send_single_protobuf_message(&mut stream, &request).await?;
         // Rithmic System Info Response 17
-        // Step 2: Read the full message based on the length
-        let message: ResponseRithmicSystemInfo = RithmicApiClient::read_single_protobuf_message(&mut stream).await?;
-        println!("{:?}", message);
-        // Now we have the system name we can do the handshake
-        Ok(message.system_name)
+        let response: ResponseRithmicSystemInfo = self.send_and_read(&mut stream, &request, "RequestRithmicSystemInfo").await?;
+        let _ = stream.close(None).await;
+
+        match RithmicResponseCode::parse(&response.rp_code) {
+            Some(RithmicResponseCode::Success) | None => {}
+            Some(code) => return Err(RithmicApiError::ServerRejected { template_id: response.template_id, code })
+        }
+        // has_aggregated_quotes is a parallel list, a missing entry means the system has no aggregated quotes
+        Ok(response.system_name
+            .into_iter()
+            .enumerate()
+            .map(|(i, system_name)| RithmicSystemInfo {
+                system: RithmicSystem::from(system_name),
+                has_aggregated_quotes: response.has_aggregated_quotes.get(i).copied().unwrap_or(false),
+            })
+            .collect())
+    }
+
+    /// The uri for the server in the credentials, from `servers.toml`.
+    fn server_domain(&self) -> Result<&String, RithmicApiError> {
+        match self.server_domains.get(&self.credentials.server_name) {
+            None => Err(RithmicApiError::ServerErrorDebug(format!("No server domain found, check server.toml for: {:?}", self.credentials.server_name))),
+            Some(domain) => Ok(domain)
+        }
+    }
+
+    /// List the gateways available for the system in the credentials.
+    pub async fn list_gateways(&self) -> Result<Vec<Gateway>, RithmicApiError> {
+        let domain = self.server_domain()?;
+        let mut stream = connect_websocket(domain, &self.config.timeouts).await?;
+        // Rithmic System Gateway Info Request 20 From Client
+        let request = RequestRithmicSystemGatewayInfo {
+            template_id: TemplateId::RequestRithmicSystemGatewayInfo.id(),
+            user_msg: vec![],
+            system_name: Some(self.credentials.system_name.to_string()),
+        };
+        // Rithmic System Gateway Info Response 21
+        let response: ResponseRithmicSystemGatewayInfo = self.send_and_read(&mut stream, &request, "RequestRithmicSystemGatewayInfo").await?;
+        let _ = stream.close(None).await;
+
+        match RithmicResponseCode::parse(&response.rp_code) {
+            Some(RithmicResponseCode::Success) | None => {}
+            Some(code) => return Err(RithmicApiError::ServerRejected { template_id: response.template_id, code })
+        }
+        Ok(response.gateway_name
+            .into_iter()
+            .zip(response.gateway_uri)
+            .map(|(name, uri)| Gateway { name, uri })
+            .collect())
+    }
+
+    /// Measure the connect and heartbeat latency of each gateway, all gateways are probed at the same time.
+    pub async fn probe_gateways(&self, gateways: Vec<Gateway>) -> Vec<GatewayProbe> {
+        let probes = gateways
+            .into_iter()
+            .map(|gateway| probe_gateway(gateway, self.codec.clone(), self.config.gateway.probe_timeout));
+        futures_util::future::join_all(probes).await
+    }
+
+    /// Probe every gateway of the system and use the fastest reachable one for future logins.
+    /// A gateway override set with [`RithmicApiClient::set_gateway_override`] still takes priority.
+    pub async fn select_fastest_gateway(&self) -> Result<Gateway, RithmicApiError> {
+        let gateways = self.list_gateways().await?;
+        let probes = self.probe_gateways(gateways).await;
+        let gateway = match fastest(&probes) {
+            Some(probe) => probe.gateway.clone(),
+            None => return Err(RithmicApiError::Disconnected(format!("No reachable gateway for {}", self.credentials.system_name)))
+        };
+        *self.selected_gateway.write().unwrap() = Some(gateway.clone());
+        Ok(gateway)
+    }
+
+    /// The gateway chosen by [`RithmicApiClient::select_fastest_gateway`].
+    pub fn selected_gateway(&self) -> Option<Gateway> {
+        self.selected_gateway.read().unwrap().clone()
+    }
+
+    /// Connect to this uri for every future login, None to go back to the selected gateway or `servers.toml`.
+    pub fn set_gateway_override(&self, uri: Option<String>) {
+        *self.gateway_override.write().unwrap() = uri;
+    }
+
+    /// The uri used to log in: the override, else the selected gateway, else the uri from `servers.toml`.
+    /// If auto select is enabled in the [`ClientConfig`] the gateways are probed on first use.
+    pub async fn gateway_uri(&self) -> Result<String, RithmicApiError> {
+        if let Some(uri) = self.gateway_override.read().unwrap().clone() {
+            return Ok(uri);
+        }
+        if let Some(gateway) = self.selected_gateway() {
+            return Ok(gateway.uri);
+        }
+        if self.config.gateway.auto_select {
+            let _selection = self.gateway_selection.lock().await;
+            // another login may have selected a gateway while we waited
+            if let Some(gateway) = self.selected_gateway() {
+                return Ok(gateway.uri);
+            }
+            match self.select_fastest_gateway().await {
+                Ok(gateway) => return Ok(gateway.uri),
+                Err(e) => eprintln!("Failed to select a gateway, using servers.toml: {}", e),
+            }
+        }
+        self.server_domain().cloned()
     }
 
     /// Connect to the desired plant and sign in with your credentials.
+    /// Each step is bounded by the `timeouts` in the [`ClientConfig`], dropping the future drops the partly opened connection.
+    /// Returns the stream with the details from the login response, a rejected login returns [`RithmicApiError::LoginRejected`].
     pub async fn connect_and_login (
         &self,
         plant: SysInfraType,
-    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, RithmicApiError> {
+    ) -> Result<LoginSession, RithmicApiError> {
 
-        let domain = match self.server_domains.get(&self.credentials.server_name) {
-            None => return Err(RithmicApiError::ServerErrorDebug(format!("No server domain found, check server.toml for: {:?}", self.credentials.server_name))),
-            Some(domain) => domain
-        };
+        let domain = self.gateway_uri().await?;
 
-        let (mut stream, _) = match connect_async(domain).await {
-            Ok((stream, response)) => (stream, response),
-            Err(e) => return Err(RithmicApiError::ServerErrorDebug(format!("Failed to connect to rithmic, for login: {}", e)))
-        };
+        let mut stream = connect_websocket(&domain, &self.config.timeouts).await?;
 
         let aggregated_quotes = match self.credentials.aggregated_quotes {
             true => Some(true),
@@ -153,7 +335,7 @@ impl RithmicApiClient {
         // After handshake, we can send confidential data
         // Login Request 10 From Client
         let login_request = RequestLogin {
-            template_id: 10,
+            template_id: TemplateId::RequestLogin.id(),
             template_version: Some(TEMPLATE_VERSION.to_string()),
             user_msg: vec![],
             user: Some(self.credentials.user.clone()),
@@ -168,50 +350,238 @@ impl RithmicApiClient {
             aggregated_quotes,
         };
 
-        RithmicApiClient::send_single_protobuf_message(&mut stream, &login_request).await?;
-
         // Login Response 11 From Server
-        let response: ResponseLogin = RithmicApiClient::read_single_protobuf_message(&mut stream).await?;
-        if response.rp_code.is_empty() {
-            eprintln!("{:?}",response);
+        let response: ResponseLogin = self.send_and_read(&mut stream, &login_request, &format!("RequestLogin to {:?}", plant)).await?;
+        LoginSession::from_response(plant, stream, response)
+    }
+
+    /// Connect to the desired plant, sign in and hand the stream to a [`PlantConnection`].
+    /// The connection is kept by the client and can be retrieved with [`RithmicApiClient::plant_connection`].
+    /// If the heartbeat is enabled in the [`ClientConfig`] it is started using the interval from the login response.
+    /// The login details are available from [`PlantConnection::login_info`].
+    /// If reconnect is enabled, a lost connection is logged in again with the same handle and its subscriptions are sent again.
+    /// Fails with [`RithmicApiError::SessionAlreadyActive`] if the plant is already connected.
+    pub async fn connect_plant(
+        self: &Arc<Self>,
+        plant: SysInfraType,
+    ) -> Result<PlantConnection, RithmicApiError> {
+        let plant_login = self.plant_logins.entry(plant).or_default().clone();
+        // a parallel call for the plant waits here and then sees the connection stored by the first
+        let _plant_login = plant_login.lock().await;
+        // a second login by the same user would force the first one out
+        if let Some(connection) = self.plant_connection(plant) {
+            if !connection.state().is_terminal() {
+                return Err(RithmicApiError::SessionAlreadyActive { user: self.credentials.user.clone(), plants: vec![plant] })
+            }
+        }
+        let session = self.connect_and_login(plant).await?;
+        let login_interval = session.info.heartbeat_interval;
+        let reconnect = self.config.reconnect.clone().map(|policy| {
+            // a weak reference so the connection task does not keep the client alive
+            let client = Arc::downgrade(self);
+            let reconnect: Reconnect = Arc::new(move |plant| {
+                let client = client.clone();
+                Box::pin(async move {
+                    match client.upgrade() {
+                        Some(client) => client.connect_and_login(plant).await,
+                        None => Err(RithmicApiError::Disconnected("RithmicApiClient has been dropped".to_string()))
+                    }
+                })
+            });
+            (policy, reconnect)
+        });
+        let rate_limiter = self.config.rate_limit.as_ref().map(RateLimiter::new);
+        let connection = PlantConnection::new(session, self.codec.clone(), reconnect, rate_limiter);
+        if self.config.heartbeat.enabled {
+            let interval = self.config.heartbeat.interval_override
+                .or(login_interval)
+                .unwrap_or(DEFAULT_HEARTBEAT_INTERVAL);
+            connection.start_heartbeat(interval, self.config.heartbeat.stale_after_intervals);
         }
-        if response.rp_code[0] != "0".to_string() {
-            eprintln!("{:?}",response);
+        tokio::task::spawn(track_accounts(self.accounts.clone(), connection.subscribe(), connection.watch_state()));
+        if plant == SysInfraType::TickerPlant {
+            tokio::task::spawn(track_quotes(self.quotes.clone(), connection.subscribe(), connection.watch_state()));
+            // failures are logged, the bits stay held and are sent again with the next connection
+            let _ = self.market_data.attach(connection.clone()).await;
+            tokio::task::spawn(track_front_months(self.front_months.clone(), connection.downgrade(), connection.subscribe(), connection.watch_state(), self.config.timeouts.request));
         }
+        self.plant_connections.insert(plant, connection.clone());
+        Ok(connection)
+    }
+
+    /// The connection created by [`RithmicApiClient::connect_plant`] for this plant.
+    pub fn plant_connection(&self, plant: SysInfraType) -> Option<PlantConnection> {
+        self.plant_connections.get(&plant).map(|connection| connection.clone())
+    }
+
+    pub fn credentials(&self) -> &RithmicCredentials {
+        &self.credentials
+    }
+
+    /// The accounts from every `ResponseAccountList` and `UserAccountUpdate` received on a connected plant.
+    pub fn accounts(&self) -> AccountCache {
+        self.accounts.clone()
+    }
+
+    /// The latest `BestBidOffer` and `LastTrade` of every symbol subscribed on the ticker plant.
+    pub fn quotes(&self) -> QuoteCache {
+        self.quotes.clone()
+    }
 
-        match response.fcm_id {
-            Some(fcm_id) => *self.fcm_id.write().await = Some(fcm_id),
-            None => {}
+    /// Shared market data subscriptions on the ticker plant, see [`MarketDataSubscriptions`].
+    pub fn market_data(&self) -> MarketDataSubscriptions {
+        self.market_data.clone()
+    }
+
+    /// The reference data fetched so far, see [`RithmicApiClient::instrument`].
+    pub fn instruments(&self) -> InstrumentCache {
+        self.instruments.clone()
+    }
+
+    /// The reference data of a symbol, requested on the ticker plant the first time and cached afterwards.
+    pub async fn instrument(&self, symbol: &str, exchange: &str) -> Result<Instrument, RithmicApiError> {
+        if let Some(instrument) = self.instruments.get(symbol, exchange) {
+            return Ok(instrument);
         }
+        let connection = match self.plant_connection(SysInfraType::TickerPlant) {
+            Some(connection) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+        };
+        self.instruments.fetch(&connection, symbol, exchange, self.config.timeouts.request).await
+    }
+
+    /// Search the symbols on the ticker plant.
+    pub async fn search_symbols(&self, search: &InstrumentSearch) -> Result<Vec<Instrument>, RithmicApiError> {
+        let connection = match self.plant_connection(SysInfraType::TickerPlant) {
+            Some(connection) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+        };
+        self.instruments.search(&connection, search, self.config.timeouts.request).await
+    }
 
-        match response.ib_id {
-            Some(fcm_id) => *self.ib_id.write().await = Some(fcm_id),
-            None => {}
+    /// The front month contracts resolved so far and their rollovers, see [`RithmicApiClient::front_month`].
+    pub fn front_months(&self) -> FrontMonthResolver {
+        self.front_months.clone()
+    }
+
+    /// The current front month of a product root, eg: `ES` on `CME`, kept current from `FrontMonthContractUpdate` once resolved.
+    pub async fn front_month(&self, root: &str, exchange: &str) -> Result<FrontMonth, RithmicApiError> {
+        if let Some(contract) = self.front_months.get(root, exchange) {
+            return Ok(contract);
         }
+        let connection = match self.plant_connection(SysInfraType::TickerPlant) {
+            Some(connection) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+        };
+        self.front_months.resolve(&connection, root, exchange, self.config.timeouts.request).await
+    }
 
-        if let Some(handshake_duration) = response.heartbeat_interval {
-            self.heartbeat_interval_seconds.insert(plant, handshake_duration as u64);
+    /// The options of an underlying, or of one expiration of it, requested on the ticker plant.
+    /// The contracts are added to [`RithmicApiClient::instruments`], call `save()` on it to write them to the instrument file.
+    pub async fn option_chain(&self, underlying_symbol: &str, exchange: &str, expiration_date: Option<&str>) -> Result<OptionChain, RithmicApiError> {
+        let connection = match self.plant_connection(SysInfraType::TickerPlant) {
+            Some(connection) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+        };
+        let chain = request_option_chain(&connection, underlying_symbol, exchange, expiration_date, self.config.timeouts.request).await?;
+        for instrument in chain.contracts() {
+            // reference data has more fields, do not replace it
+            if self.instruments.get(&instrument.symbol, &instrument.exchange).is_none() {
+                self.instruments.insert(instrument.clone());
+            }
         }
+        Ok(chain)
+    }
 
-        Ok(stream)
+    /// Subscribe the market data of every option of an underlying, or of one expiration of it, as a group.
+    /// The group is unsubscribed when the guard is released or dropped.
+    pub async fn subscribe_option_chain(&self, underlying_symbol: &str, exchange: &str, expiration_date: Option<&str>, update_bits: u32) -> Result<MarketDataGuard, RithmicApiError> {
+        self.market_data.subscribe_underlying(underlying_symbol, exchange, expiration_date, update_bits).await
+    }
+
+    /// Download the time bars of a symbol between two times in seconds since the unix epoch, using the connected history plant.
+    /// Long ranges are requested in chunks and bars cut off by the server are resumed, the bars are sorted by time without duplicates.
+    pub async fn download_time_bars(
+        &self,
+        symbol: &str,
+        exchange: &str,
+        bar_type: BarType,
+        period: i32,
+        start: i32,
+        end: i32,
+    ) -> Result<Vec<TimeBar>, RithmicApiError> {
+        let download = TimeBarDownload::new(symbol.to_string(), exchange.to_string(), bar_type, period, start, end);
+        self.download_time_bars_with_progress(&download, |_| {}).await
+    }
+
+    /// Like [`RithmicApiClient::download_time_bars`] with the chunk sizes of the download, `progress` is called after every response.
+    pub async fn download_time_bars_with_progress(
+        &self,
+        download: &TimeBarDownload,
+        progress: impl FnMut(&DownloadProgress),
+    ) -> Result<Vec<TimeBar>, RithmicApiError> {
+        let connection = match self.plant_connection(SysInfraType::HistoryPlant) {
+            Some(connection) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: HistoryPlant".to_string()))
+        };
+        download_time_bars(&connection, download, self.config.timeouts.request, progress).await
+    }
+
+    /// Send a request on a plant connected with [`RithmicApiClient::connect_plant`] and wait for every response to it.
+    /// Fails with [`RithmicApiError::Timeout`] if the final response does not arrive within the configured request timeout.
+    pub async fn request<R: RithmicRequest>(
+        &self,
+        plant: SysInfraType,
+        request: R,
+    ) -> Result<Vec<R::Response>, RithmicApiError> {
+        let connection = match self.plant_connection(plant) {
+            Some(connection) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug(format!("No connection for plant: {:?}", plant)))
+        };
+        connection.request(request, self.config.timeouts.request).await
+    }
+
+    /// Keep a market by order book for the symbol current, using the connected ticker plant.
+    pub async fn track_market_by_order(&self, symbol: String, exchange: String) -> Result<MarketByOrderHandle, RithmicApiError> {
+        track_market_by_order(&self.market_data, symbol, exchange, self.config.timeouts.request).await
+    }
+
+    /// Request the volume at price of a symbol and keep it current with its trades, using the connected ticker plant.
+    pub async fn track_volume_profile(&self, symbol: String, exchange: String) -> Result<VolumeProfileHandle, RithmicApiError> {
+        track_volume_profile(&self.market_data, symbol, exchange, self.config.timeouts.request).await
+    }
+
+    /// Keep a price level book for the symbol current, using the connected ticker plant.
+    pub async fn track_price_levels(&self, symbol: String, exchange: String) -> Result<PriceLevelHandle, RithmicApiError> {
+        track_price_levels(&self.market_data, symbol, exchange).await
+    }
+
+    /// Signs out of the plant and closes the socket once the logout request has been written.
+    pub async fn shutdown_connection(
+        &self,
+        plant: SysInfraType,
+    ) -> Result<(), RithmicApiError> {
+        let connection = match self.plant_connections.remove(&plant) {
+            Some((_, connection)) => connection,
+            None => return Err(RithmicApiError::ClientErrorDebug(format!("No connection for plant: {:?}", plant)))
+        };
+        //Logout Request 12
+        let logout_request = RequestLogout {
+            template_id: TemplateId::RequestLogout.id(),
+            user_msg: vec![format!("{} Signing Out", self.credentials.app_name)],
+        };
+        let result = connection.send(&logout_request).await;
+        connection.close();
+        result
     }
 
     /// Send a message on the write half of the plant stream.
     pub async fn send_message<T: ProstMessage>(
         &self,
-        mut write_stream: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+        write_stream: &mut SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
         message: T
     ) -> Result<(), RithmicApiError> {
-        let mut buf = Vec::new();
-
-        match message.encode(&mut buf) {
-            Ok(_) => {}
-            Err(e) => return Err(RithmicApiError::ServerErrorDebug(format!("Failed to encode RithmicMessage: {}", e)))
-        }
-
-        let length = buf.len() as u32;
-        let mut prefixed_msg = length.to_be_bytes().to_vec();
-        prefixed_msg.extend(buf);
+        let prefixed_msg = self.codec.encode(&message)?;
 
         match write_stream.send(Message::Binary(prefixed_msg)).await {
             Ok(_) => {
@@ -224,24 +594,15 @@ impl RithmicApiClient {
     /// Signs out of rithmic with the specific plant safely shuts down the web socket. removing references from our api object.
     pub async fn shutdown_plant(
         &self,
-        mut write_stream: SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
+        write_stream: &mut SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>,
     ) -> Result<(), RithmicApiError> {
         //Logout Request 12
         let logout_request = RequestLogout {
-            template_id: 12,
+            template_id: TemplateId::RequestLogout.id(),
             user_msg: vec![format!("{} Signing Out", self.credentials.app_name)],
         };
 
-        let mut buf = Vec::new();
-        match logout_request.encode(&mut buf) {
-            Ok(_) => {}
-            Err(e) => return Err(RithmicApiError::ServerErrorDebug(format!("Failed to encode RithmicMessage: {}", e)))
-        }
-
-
-        let length = buf.len() as u32;
-        let mut prefixed_msg = length.to_be_bytes().to_vec();
-        prefixed_msg.extend(buf);
+        let prefixed_msg = self.codec.encode(&logout_request)?;
 
         match write_stream.send(Message::Binary(prefixed_msg)).await {
             Ok(_) => Ok(()),
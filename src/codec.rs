@@ -0,0 +1,241 @@
+use prost::{Message as ProstMessage};
+use crate::errors::RithmicApiError;
+use crate::messages::{decode_message, RithmicMessage};
+
+/// Every frame starts with a 4-byte big endian length header.
+pub const HEADER_LENGTH: usize = 4;
+
+/// Default upper bound for a single frame, large enough for the biggest history and order replays rithmic sends.
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// Framing layer for rithmic messages.
+/// Each protobuf payload is prefixed with its length as a 4-byte big endian u32.
+/// A single WebSocket binary message may carry several frames, or only part of one, so the decoder keeps any incomplete frame until the rest arrives.
+#[derive(Debug, Clone)]
+pub struct RithmicCodec {
+    max_frame_size: usize,
+    buffer: Vec<u8>,
+}
+
+impl Default for RithmicCodec {
+    fn default() -> Self {
+        RithmicCodec::new(DEFAULT_MAX_FRAME_SIZE)
+    }
+}
+
+impl RithmicCodec {
+    /// Create a codec that rejects any frame with a payload larger than `max_frame_size` bytes.
+    pub fn new(max_frame_size: usize) -> Self {
+        Self {
+            max_frame_size,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn max_frame_size(&self) -> usize {
+        self.max_frame_size
+    }
+
+    /// Returns true if the decoder is holding the start of a frame that has not been completed yet.
+    pub fn has_partial_frame(&self) -> bool {
+        !self.buffer.is_empty()
+    }
+
+    /// Discard any buffered partial frame, used after a reconnect so stale bytes are not joined to the new stream.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    /// Encode a message and prefix it with its length header.
+    pub fn encode<T: ProstMessage>(&self, message: &T) -> Result<Vec<u8>, RithmicApiError> {
+        self.frame(&message.encode_to_vec())
+    }
+
+    /// Encode an already decoded message, eg: to replay a request after a reconnect.
+    pub fn encode_message(&self, message: &RithmicMessage) -> Result<Vec<u8>, RithmicApiError> {
+        self.frame(&message.encode_to_vec())
+    }
+
+    /// Prefix an encoded protobuf payload with its length header.
+    pub fn frame(&self, payload: &[u8]) -> Result<Vec<u8>, RithmicApiError> {
+        self.check_length(payload.len())?;
+        let mut prefixed_msg = Vec::with_capacity(HEADER_LENGTH + payload.len());
+        prefixed_msg.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+        prefixed_msg.extend_from_slice(payload);
+        Ok(prefixed_msg)
+    }
+
+    /// Feed the bytes of one WebSocket binary message and return the payload of every frame that is now complete.
+    /// Incomplete frames are kept until the next call.
+    /// On error the internal buffer is cleared, since the stream can no longer be trusted to be aligned on a frame boundary.
+    pub fn decode(&mut self, bytes: &[u8]) -> Result<Vec<Vec<u8>>, RithmicApiError> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut frames = Vec::new();
+        let mut position = 0;
+        while self.buffer.len() - position >= HEADER_LENGTH {
+            let length = match self.read_header(&self.buffer[position..]) {
+                Ok(length) => length,
+                Err(e) => {
+                    self.buffer.clear();
+                    return Err(e);
+                }
+            };
+            let end = position + HEADER_LENGTH + length;
+            if self.buffer.len() < end {
+                break;
+            }
+            frames.push(self.buffer[position + HEADER_LENGTH..end].to_vec());
+            position = end;
+        }
+        self.buffer.drain(..position);
+        Ok(frames)
+    }
+
+    /// Decode every complete frame into its concrete message type.
+    /// Each frame is decoded on its own, a frame that fails does not lose the others, its error is returned in its place.
+    pub fn decode_messages(&mut self, bytes: &[u8]) -> Result<Vec<Result<RithmicMessage, RithmicApiError>>, RithmicApiError> {
+        Ok(self.decode(bytes)?
+            .iter()
+            .map(|frame| decode_message(frame))
+            .collect())
+    }
+
+    /// Decode a WebSocket binary message that must contain exactly one complete frame.
+    /// The length header has to match the payload length exactly.
+    pub fn decode_single(&self, bytes: &[u8]) -> Result<Vec<u8>, RithmicApiError> {
+        if bytes.len() < HEADER_LENGTH {
+            return Err(RithmicApiError::InvalidFrame(format!("Frame of {} bytes is too short for the length header", bytes.len())))
+        }
+        let length = self.read_header(bytes)?;
+        let payload = &bytes[HEADER_LENGTH..];
+        if payload.len() != length {
+            return Err(RithmicApiError::InvalidFrame(format!("Length header is {} but payload is {} bytes", length, payload.len())))
+        }
+        Ok(payload.to_vec())
+    }
+
+    fn read_header(&self, bytes: &[u8]) -> Result<usize, RithmicApiError> {
+        let mut length_buf = [0u8; HEADER_LENGTH];
+        length_buf.copy_from_slice(&bytes[..HEADER_LENGTH]);
+        let length = u32::from_be_bytes(length_buf) as usize;
+        self.check_length(length)?;
+        Ok(length)
+    }
+
+    fn check_length(&self, length: usize) -> Result<(), RithmicApiError> {
+        if length == 0 {
+            return Err(RithmicApiError::InvalidFrame("Frame has an empty payload".to_string()))
+        }
+        if length > self.max_frame_size {
+            return Err(RithmicApiError::FrameTooLarge { length, max: self.max_frame_size })
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rithmic_proto_objects::rti::RequestHeartbeat;
+    use crate::templates::TemplateId;
+
+    fn heartbeat() -> RequestHeartbeat {
+        RequestHeartbeat {
+            template_id: TemplateId::RequestHeartbeat.id(),
+            user_msg: vec!["test".to_string()],
+            ssboe: None,
+            usecs: None,
+        }
+    }
+
+    #[test]
+    fn decode_returns_the_encoded_payload() {
+        let mut codec = RithmicCodec::default();
+        let frame = codec.encode(&heartbeat()).unwrap();
+        let frames = codec.decode(&frame).unwrap();
+        assert_eq!(frames, vec![heartbeat().encode_to_vec()]);
+        assert!(!codec.has_partial_frame());
+    }
+
+    #[test]
+    fn decode_splits_several_frames_in_one_message() {
+        let mut codec = RithmicCodec::default();
+        let mut bytes = codec.frame(b"first").unwrap();
+        bytes.extend(codec.frame(b"second").unwrap());
+        let frames = codec.decode(&bytes).unwrap();
+        assert_eq!(frames, vec![b"first".to_vec(), b"second".to_vec()]);
+    }
+
+    #[test]
+    fn decode_keeps_a_partial_frame_until_the_rest_arrives() {
+        let mut codec = RithmicCodec::default();
+        let bytes = codec.frame(b"payload").unwrap();
+        // split inside the header, then inside the payload
+        assert!(codec.decode(&bytes[..2]).unwrap().is_empty());
+        assert!(codec.has_partial_frame());
+        assert!(codec.decode(&bytes[2..6]).unwrap().is_empty());
+        assert!(codec.has_partial_frame());
+        assert_eq!(codec.decode(&bytes[6..]).unwrap(), vec![b"payload".to_vec()]);
+        assert!(!codec.has_partial_frame());
+    }
+
+    #[test]
+    fn decode_returns_complete_frames_and_keeps_the_trailing_partial_one() {
+        let mut codec = RithmicCodec::default();
+        let mut bytes = codec.frame(b"first").unwrap();
+        let second = codec.frame(b"second").unwrap();
+        bytes.extend_from_slice(&second[..5]);
+        assert_eq!(codec.decode(&bytes).unwrap(), vec![b"first".to_vec()]);
+        assert!(codec.has_partial_frame());
+        assert_eq!(codec.decode(&second[5..]).unwrap(), vec![b"second".to_vec()]);
+    }
+
+    #[test]
+    fn decode_rejects_an_oversized_frame_and_clears_the_buffer() {
+        let mut codec = RithmicCodec::new(8);
+        assert!(codec.decode(&[0, 0]).unwrap().is_empty());
+        let result = codec.decode(&[0, 9, 1, 2]);
+        assert!(matches!(result, Err(RithmicApiError::FrameTooLarge { length: 9, max: 8 })));
+        assert!(!codec.has_partial_frame());
+    }
+
+    #[test]
+    fn decode_rejects_an_empty_payload() {
+        let mut codec = RithmicCodec::default();
+        assert!(matches!(codec.decode(&[0, 0, 0, 0]), Err(RithmicApiError::InvalidFrame(_))));
+        assert!(!codec.has_partial_frame());
+    }
+
+    #[test]
+    fn frame_rejects_an_oversized_payload() {
+        let codec = RithmicCodec::new(4);
+        assert!(matches!(codec.frame(b"12345"), Err(RithmicApiError::FrameTooLarge { length: 5, max: 4 })));
+        assert!(matches!(codec.frame(b""), Err(RithmicApiError::InvalidFrame(_))));
+    }
+
+    #[test]
+    fn decode_messages_keeps_good_frames_when_one_fails() {
+        let mut codec = RithmicCodec::default();
+        let mut bytes = codec.encode(&heartbeat()).unwrap();
+        bytes.extend(codec.frame(&[0xff]).unwrap());
+        bytes.extend(codec.encode(&heartbeat()).unwrap());
+        let messages = codec.decode_messages(&bytes).unwrap();
+        assert_eq!(messages.len(), 3);
+        assert!(matches!(messages[0], Ok(RithmicMessage::RequestHeartbeat(_))));
+        assert!(messages[1].is_err());
+        assert!(matches!(messages[2], Ok(RithmicMessage::RequestHeartbeat(_))));
+    }
+
+    #[test]
+    fn decode_single_requires_exactly_one_frame() {
+        let codec = RithmicCodec::default();
+        let frame = codec.frame(b"payload").unwrap();
+        assert_eq!(codec.decode_single(&frame).unwrap(), b"payload".to_vec());
+        assert!(matches!(codec.decode_single(&frame[..2]), Err(RithmicApiError::InvalidFrame(_))));
+        assert!(matches!(codec.decode_single(&frame[..6]), Err(RithmicApiError::InvalidFrame(_))));
+        let mut two = frame.clone();
+        two.extend_from_slice(&frame);
+        assert!(matches!(codec.decode_single(&two), Err(RithmicApiError::InvalidFrame(_))));
+    }
+}
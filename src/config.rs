@@ -0,0 +1,54 @@
+use std::path::PathBuf;
+use crate::codec::DEFAULT_MAX_FRAME_SIZE;
+use crate::errors::RithmicApiError;
+use crate::gateways::GatewayConfig;
+use crate::heartbeat::HeartbeatConfig;
+use crate::rate_limit::RateLimitConfig;
+use crate::reconnect::ReconnectPolicy;
+use crate::timeouts::Timeouts;
+
+/// Behaviour of a [`crate::api_client::RithmicApiClient`] and the plant connections it creates.
+#[derive(Clone, Debug)]
+pub struct ClientConfig {
+    /// Managed heartbeat for connections created with `connect_plant`.
+    pub heartbeat: HeartbeatConfig,
+    /// Backoff used to log in again when a connection created with `connect_plant` is lost, None disables reconnecting.
+    pub reconnect: Option<ReconnectPolicy>,
+    /// Timeouts for connecting, logging in and waiting for responses.
+    pub timeouts: Timeouts,
+    /// Outgoing request budgets for connections created with `connect_plant`, None sends without limits.
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Which gateway to log in to.
+    pub gateway: GatewayConfig,
+    /// TOML file the instrument reference data is loaded from, written by `InstrumentCache::save`, None keeps it in memory only.
+    pub instrument_file: Option<PathBuf>,
+    /// Frames with a larger payload are rejected, for the login streams and every plant connection.
+    pub max_frame_size: usize,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        Self {
+            heartbeat: HeartbeatConfig::default(),
+            reconnect: Some(ReconnectPolicy::default()),
+            timeouts: Timeouts::default(),
+            rate_limit: Some(RateLimitConfig::default()),
+            gateway: GatewayConfig::default(),
+            instrument_file: None,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+}
+
+impl ClientConfig {
+    /// Checks the values a client can not work with, eg: a `max_frame_size` of 0 or a rate limit that never refills.
+    pub fn validate(&self) -> Result<(), RithmicApiError> {
+        if self.max_frame_size == 0 {
+            return Err(RithmicApiError::InvalidConfig("max_frame_size must be greater than 0".to_string()));
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.validate()?;
+        }
+        Ok(())
+    }
+}
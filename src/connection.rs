@@ -0,0 +1,673 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+use futures_util::future::BoxFuture;
+use futures_util::{SinkExt, Stream, StreamExt};
+use prost::{Message as ProstMessage};
+use tokio::net::TcpStream;
+use tokio::sync::{broadcast, mpsc, oneshot, watch};
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::protocol::Message;
+use crate::api_client::extract_template_id;
+use crate::codec::{RithmicCodec, HEADER_LENGTH};
+use crate::control::{ControlEvent, CONTROL_CHANNEL_CAPACITY};
+use crate::errors::RithmicApiError;
+use crate::heartbeat::{run_heartbeat, HeartbeatStatus};
+use crate::rate_limit::{RateLimitMode, RateLimiter, RequestClass};
+use crate::messages::{decode_message, RithmicMessage};
+use crate::reconnect::{ReconnectPolicy, SubscriptionRegistry};
+use crate::response_code::RithmicResponseCode;
+use crate::request::{next_correlation_token, RithmicRequest, CORRELATION_PREFIX};
+use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
+use crate::session::{LoginInfo, LoginSession};
+
+/// How many decoded messages a slow subscriber can fall behind before it starts missing messages.
+pub const INBOUND_CHANNEL_CAPACITY: usize = 10_000;
+
+/// Connects and logs in to a plant again after the connection was lost.
+pub(crate) type Reconnect = Arc<dyn Fn(SysInfraType) -> BoxFuture<'static, Result<LoginSession, RithmicApiError>> + Send + Sync>;
+
+/// The state of a plant connection, published on every transition.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConnectionState {
+    /// Logged in and ready to send.
+    Connected,
+    /// The connection was lost and the supervisor is logging in again, `attempt` starts at 1.
+    Reconnecting { attempt: u32 },
+    /// The connection was lost and will not be reconnected, either reconnect is disabled or every attempt failed.
+    Disconnected,
+    /// Closed by us.
+    Closed,
+    /// The server sent `ForcedLogout`, eg: the same user logged in from another machine. Not reconnected, since logging in again would log out the other session.
+    LoggedOut { reason: String },
+}
+
+impl ConnectionState {
+    /// Returns true if the connection will never be usable again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, ConnectionState::Disconnected | ConnectionState::Closed | ConnectionState::LoggedOut { .. })
+    }
+}
+
+/// Work for the task that owns the socket.
+enum Outbound {
+    /// A framed message ready to be written, `class` is set if it still has to wait for the rate limiter.
+    Frame { frame: Vec<u8>, class: Option<RequestClass> },
+    /// Close the socket once everything queued before this has been written.
+    Close,
+}
+
+/// A request waiting for its final response.
+struct PendingRequest {
+    /// Responses received so far, each marked with `rq_handler_rp_code`.
+    rows: Vec<RithmicMessage>,
+    /// The template of the request, reported when it is answered with a `Reject`.
+    template_id: i32,
+    sender: oneshot::Sender<Result<Vec<RithmicMessage>, RithmicApiError>>,
+}
+
+/// Removes the pending request when the future awaiting it completes or is dropped.
+struct PendingGuard<'a> {
+    shared: &'a Shared,
+    token: &'a str,
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        self.shared.pending.lock().unwrap().remove(self.token);
+    }
+}
+
+/// How the socket loop ended.
+#[derive(PartialEq)]
+enum SocketExit {
+    Closed,
+    Lost,
+    ForcedLogout,
+}
+
+/// How long to wait for the server to close the socket after a forced logout, the close frame may carry the reason.
+const FORCED_LOGOUT_CLOSE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A cloneable handle to a logged in plant.
+/// The socket is owned by a background task, writes from every clone are queued and written in order, so a frame is never interleaved with another.
+/// The socket is closed once every handle is dropped, background tasks such as the heartbeat only hold a [`WeakPlantConnection`].
+/// Incoming frames are decoded into [`RithmicMessage`]s and broadcast to every subscriber.
+/// If a [`ReconnectPolicy`] is set, a lost connection is logged in again and every active subscription is sent again, the handle stays valid throughout.
+#[derive(Clone)]
+pub struct PlantConnection {
+    inner: Arc<PlantConnectionInner>,
+}
+
+/// A handle that does not keep the connection open, held by background tasks that should stop once every [`PlantConnection`] is dropped.
+#[derive(Clone)]
+pub(crate) struct WeakPlantConnection {
+    inner: Weak<PlantConnectionInner>,
+}
+
+impl WeakPlantConnection {
+    /// None once every [`PlantConnection`] has been dropped.
+    pub(crate) fn upgrade(&self) -> Option<PlantConnection> {
+        self.inner.upgrade().map(|inner| PlantConnection { inner })
+    }
+}
+
+struct PlantConnectionInner {
+    shared: Arc<Shared>,
+    outbound: mpsc::UnboundedSender<Outbound>,
+    /// When a frame was last queued, heartbeats are only needed when nothing else is being sent.
+    last_sent: Mutex<Instant>,
+    heartbeat: watch::Sender<HeartbeatStatus>,
+}
+
+/// State shared between the handles and the socket task.
+struct Shared {
+    plant: SysInfraType,
+    /// Replaced with the new login details after every reconnect.
+    login: Mutex<LoginInfo>,
+    codec: RithmicCodec,
+    inbound: broadcast::Sender<RithmicMessage>,
+    control: broadcast::Sender<ControlEvent>,
+    state: watch::Sender<ConnectionState>,
+    subscriptions: Mutex<SubscriptionRegistry>,
+    rate_limiter: Option<RateLimiter>,
+    /// Requests sent with [`PlantConnection::request`], keyed by correlation token.
+    pending: Mutex<HashMap<String, PendingRequest>>,
+}
+
+impl PlantConnection {
+    /// Take ownership of a logged in stream and start the task that reads and writes it.
+    pub(crate) fn new(
+        session: LoginSession,
+        codec: RithmicCodec,
+        reconnect: Option<(ReconnectPolicy, Reconnect)>,
+        rate_limiter: Option<RateLimiter>,
+    ) -> Self {
+        let (outbound, outbound_receiver) = mpsc::unbounded_channel();
+        let (inbound, _) = broadcast::channel(INBOUND_CHANNEL_CAPACITY);
+        let LoginSession { stream, info } = session;
+        let shared = Arc::new(Shared {
+            plant: info.plant,
+            login: Mutex::new(info),
+            codec,
+            inbound,
+            control: broadcast::channel(CONTROL_CHANNEL_CAPACITY).0,
+            state: watch::channel(ConnectionState::Connected).0,
+            subscriptions: Mutex::new(SubscriptionRegistry::default()),
+            rate_limiter,
+            pending: Mutex::new(HashMap::new()),
+        });
+
+        tokio::task::spawn(supervise(shared.clone(), stream, outbound_receiver, reconnect));
+
+        Self {
+            inner: Arc::new(PlantConnectionInner {
+                shared,
+                outbound,
+                last_sent: Mutex::new(Instant::now()),
+                heartbeat: watch::channel(HeartbeatStatus::default()).0,
+            }),
+        }
+    }
+
+    pub fn plant(&self) -> SysInfraType {
+        self.inner.shared.plant
+    }
+
+    pub(crate) fn downgrade(&self) -> WeakPlantConnection {
+        WeakPlantConnection { inner: Arc::downgrade(&self.inner) }
+    }
+
+    /// The details from the latest login response, eg: the fcm_id and ib_id needed for order plant requests.
+    pub fn login_info(&self) -> LoginInfo {
+        self.inner.shared.login.lock().unwrap().clone()
+    }
+
+    /// Returns true while logged in and ready to send.
+    pub fn is_connected(&self) -> bool {
+        *self.inner.shared.state.borrow() == ConnectionState::Connected
+    }
+
+    /// The current connection state.
+    pub fn state(&self) -> ConnectionState {
+        self.inner.shared.state.borrow().clone()
+    }
+
+    /// Watch connection state transitions, eg: to pause a strategy while the plant is reconnecting.
+    pub fn watch_state(&self) -> watch::Receiver<ConnectionState> {
+        self.inner.shared.state.subscribe()
+    }
+
+    /// How many subscriptions will be sent again after a reconnect.
+    pub fn active_subscriptions(&self) -> usize {
+        self.inner.shared.subscriptions.lock().unwrap().len()
+    }
+
+    /// Frame the message and queue it for the socket task.
+    /// Returns once the message is queued, messages are written in the order they were queued.
+    /// Fails while the plant is reconnecting, requests are never held back and sent late.
+    /// If a rate limit is configured the message may wait or be rejected, depending on the [`RateLimitMode`].
+    /// Cancel safe, a frame is either queued whole or not at all, only the socket task writes to the socket.
+    pub async fn send<T: ProstMessage>(&self, message: &T) -> Result<(), RithmicApiError> {
+        let frame = self.inner.shared.codec.encode(message)?;
+        self.send_frame(frame).await
+    }
+
+    /// Frame an already decoded message and queue it, see [`PlantConnection::send`].
+    pub(crate) async fn send_message(&self, message: &RithmicMessage) -> Result<(), RithmicApiError> {
+        let frame = self.inner.shared.codec.encode_message(message)?;
+        self.send_frame(frame).await
+    }
+
+    /// Apply an unsubscribe that could not be sent to the subscriptions replayed after a reconnect, so they are not subscribed again.
+    pub(crate) fn forget_subscription(&self, unsubscribe: RithmicMessage) {
+        self.inner.shared.subscriptions.lock().unwrap().record(unsubscribe);
+    }
+
+    pub(crate) async fn send_frame(&self, frame: Vec<u8>) -> Result<(), RithmicApiError> {
+        self.check_connected()?;
+        let template_id = extract_template_id(&frame[HEADER_LENGTH..]);
+        let subscription = match template_id {
+            Some(template_id) if SubscriptionRegistry::is_tracked(template_id) => decode_message(&frame[HEADER_LENGTH..]).ok(),
+            _ => None,
+        };
+
+        let mut queued_class = None;
+        if let Some(rate_limiter) = &self.inner.shared.rate_limiter {
+            let class = RequestClass::from_template_id(template_id.unwrap_or_default());
+            match rate_limiter.mode() {
+                RateLimitMode::Queue => queued_class = Some(class),
+                RateLimitMode::Delay => {
+                    rate_limiter.acquire(class).await;
+                    // the connection may have been lost while waiting
+                    self.check_connected()?;
+                }
+                RateLimitMode::Reject => {
+                    if let Err(retry_after) = rate_limiter.try_acquire(class) {
+                        return Err(RithmicApiError::RateLimited { plant: self.plant(), class, retry_after })
+                    }
+                }
+            }
+        }
+
+        match self.inner.outbound.send(Outbound::Frame { frame, class: queued_class }) {
+            Ok(_) => {
+                *self.inner.last_sent.lock().unwrap() = Instant::now();
+                if let Some(subscription) = subscription {
+                    self.inner.shared.subscriptions.lock().unwrap().record(subscription);
+                }
+                Ok(())
+            },
+            Err(_) => Err(RithmicApiError::Disconnected(format!("{:?} socket task has stopped", self.plant())))
+        }
+    }
+
+    /// Send a request and collect every response to it.
+    /// A correlation token is added to `user_msg`, responses are matched by the token rithmic echoes back.
+    /// Multi-part responses, eg: `ResponseTimeBarReplay` or `ResponseSearchSymbols`, are collected until the final response carrying `rp_code` arrives.
+    /// Responses are still broadcast to subscribers as usual.
+    /// Dropping the future before it completes forgets the request, responses that arrive later are only broadcast.
+    pub async fn request<R: RithmicRequest>(&self, request: R, timeout: Duration) -> Result<Vec<R::Response>, RithmicApiError> {
+        let responses = self.request_messages(request, timeout).await?;
+        // RequestGetInstrumentByUnderlying is also answered with keys, which are not part of the response type
+        Ok(responses
+            .into_iter()
+            .filter_map(|message| R::Response::try_from(message).ok())
+            .collect())
+    }
+
+    /// Like [`PlantConnection::request`] but returns the undecoded responses.
+    /// Returns the rows of a multi-part response, or the final response if there were no rows.
+    /// A "no data" response returns no messages, any other failure returns [`RithmicApiError::ServerRejected`].
+    pub async fn request_messages<R: RithmicRequest>(&self, mut request: R, timeout: Duration) -> Result<Vec<RithmicMessage>, RithmicApiError> {
+        let shared = &self.inner.shared;
+        let token = next_correlation_token();
+        request.user_msg_mut().push(token.clone());
+
+        let (sender, receiver) = oneshot::channel();
+        shared.pending.lock().unwrap().insert(token.clone(), PendingRequest { rows: Vec::new(), template_id: R::TEMPLATE_ID.id(), sender });
+        let _guard = PendingGuard { shared, token: &token };
+
+        self.send(&request).await?;
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(RithmicApiError::Disconnected(format!("{:?} was disconnected before {} was answered", self.plant(), R::TEMPLATE_ID))),
+            Err(_) => Err(RithmicApiError::Timeout { operation: format!("{} on {:?}", R::TEMPLATE_ID, self.plant()) }),
+        }
+    }
+
+    /// Send a request whose responses are matched by a key the server puts in them rather than by `user_msg`,
+    /// eg: the rows of `RequestResumeBars` (210) carry the `request_key` of the replay they continue.
+    /// The request itself is answered as usual, then the rows are collected until the final one carrying `rp_code`.
+    pub(crate) async fn request_keyed<R: RithmicRequest>(&self, request: R, key: &str, timeout: Duration) -> Result<Vec<RithmicMessage>, RithmicApiError> {
+        let shared = &self.inner.shared;
+        let (sender, receiver) = oneshot::channel();
+        // registered before sending, the rows may arrive before the answer to the request
+        shared.pending.lock().unwrap().insert(key.to_string(), PendingRequest { rows: Vec::new(), template_id: R::TEMPLATE_ID.id(), sender });
+        let _guard = PendingGuard { shared, token: key };
+
+        self.request(request, timeout).await?;
+        match tokio::time::timeout(timeout, receiver).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(_)) => Err(RithmicApiError::Disconnected(format!("{:?} was disconnected before {} {} was answered", self.plant(), R::TEMPLATE_ID, key))),
+            Err(_) => Err(RithmicApiError::Timeout { operation: format!("{} {} on {:?}", R::TEMPLATE_ID, key, self.plant()) }),
+        }
+    }
+
+    fn check_connected(&self) -> Result<(), RithmicApiError> {
+        match self.is_connected() {
+            true => Ok(()),
+            false => Err(RithmicApiError::Disconnected(format!("{:?} is not connected: {:?}", self.plant(), self.state())))
+        }
+    }
+
+    /// Receive every message decoded from this plant from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<RithmicMessage> {
+        self.inner.shared.inbound.subscribe()
+    }
+
+    /// Receive forced logouts, rejects and account access updates from now on.
+    /// These messages are also broadcast to [`PlantConnection::subscribe`] as usual.
+    pub fn control_events(&self) -> broadcast::Receiver<ControlEvent> {
+        self.inner.shared.control.subscribe()
+    }
+
+    /// A typed stream of every message decoded from this plant from now on.
+    /// If the consumer falls more than [`INBOUND_CHANNEL_CAPACITY`] messages behind, the oldest messages are skipped.
+    pub fn messages(&self) -> impl Stream<Item = RithmicMessage> {
+        let receiver = self.subscribe();
+        futures_util::stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(message) => return Some((message, receiver)),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Inbound message stream lagged, skipped {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return None,
+                }
+            }
+        })
+    }
+
+    /// When a message was last queued on this connection.
+    pub fn last_sent(&self) -> Instant {
+        *self.inner.last_sent.lock().unwrap()
+    }
+
+    /// Start sending `RequestHeartbeat` whenever nothing else has been sent for `interval`.
+    /// The plant is reported as stale once a heartbeat has gone unanswered for `stale_after_intervals` intervals.
+    /// The task stops when the connection is closed or disconnected for good, or when every handle is dropped.
+    pub fn start_heartbeat(&self, interval: Duration, stale_after_intervals: u32) {
+        tokio::task::spawn(run_heartbeat(self.downgrade(), self.subscribe(), self.watch_state(), interval, stale_after_intervals));
+    }
+
+    /// The latest heartbeat state, see [`PlantConnection::start_heartbeat`].
+    pub fn heartbeat_status(&self) -> HeartbeatStatus {
+        self.inner.heartbeat.borrow().clone()
+    }
+
+    /// Watch heartbeat state changes, eg: to pause a strategy while the plant is stale.
+    pub fn watch_heartbeat(&self) -> watch::Receiver<HeartbeatStatus> {
+        self.inner.heartbeat.subscribe()
+    }
+
+    /// Returns true if heartbeat responses have stopped arriving.
+    pub fn is_stale(&self) -> bool {
+        self.inner.heartbeat.borrow().is_stale
+    }
+
+    pub(crate) fn update_heartbeat_status(&self, update: impl FnOnce(&mut HeartbeatStatus)) {
+        self.inner.heartbeat.send_if_modified(|status| {
+            let before = status.clone();
+            update(status);
+            *status != before
+        });
+    }
+
+    /// Close the socket after every message already queued has been written.
+    /// The connection is not reconnected after this.
+    /// Use [`crate::api_client::RithmicApiClient::shutdown_connection`] to log out first.
+    pub fn close(&self) {
+        let _ = self.inner.outbound.send(Outbound::Close);
+    }
+}
+
+impl Shared {
+    /// Add a response to the request it answers, completing the request on the final response.
+    /// Responses are matched by the correlation token in `user_msg`, or by the `request_key` of a replay registered with [`PlantConnection::request_keyed`].
+    fn route_response(&self, message: &RithmicMessage) {
+        let mut pending = self.pending.lock().unwrap();
+        let token = message
+            .user_msg()
+            .iter()
+            .find(|user_msg| user_msg.starts_with(CORRELATION_PREFIX) && pending.contains_key(*user_msg))
+            .cloned()
+            .or_else(|| match message {
+                RithmicMessage::ResponseTimeBarReplay(response) => response.request_key.clone().filter(|key| pending.contains_key(key)),
+                _ => None,
+            });
+        let token = match &token {
+            Some(token) => token,
+            None => return,
+        };
+        let response_code = match RithmicResponseCode::parse(message.rp_code()) {
+            Some(response_code) => response_code,
+            None => {
+                if let Some(request) = pending.get_mut(token) {
+                    request.rows.push(message.clone());
+                }
+                return;
+            }
+        };
+        let request = match pending.remove(token) {
+            Some(request) => request,
+            None => return,
+        };
+        let result = match response_code {
+            RithmicResponseCode::Success if request.rows.is_empty() => Ok(vec![message.clone()]),
+            RithmicResponseCode::Success => Ok(request.rows),
+            RithmicResponseCode::NoData { .. } => Ok(Vec::new()),
+            code => {
+                let template_id = match message {
+                    RithmicMessage::Reject(_) => request.template_id,
+                    _ => message.template_id(),
+                };
+                Err(RithmicApiError::ServerRejected { template_id, code })
+            }
+        };
+        // the receiver is gone if the request was cancelled
+        let _ = request.sender.send(result);
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        self.state.send_if_modified(|current| {
+            if *current == state {
+                return false;
+            }
+            *current = state;
+            true
+        });
+    }
+}
+
+/// Runs the socket until it is closed by us, reconnecting with the policy whenever it is lost.
+async fn supervise(
+    shared: Arc<Shared>,
+    mut stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    mut outbound: mpsc::UnboundedReceiver<Outbound>,
+    reconnect: Option<(ReconnectPolicy, Reconnect)>,
+) {
+    let mut codec = RithmicCodec::new(shared.codec.max_frame_size());
+    let mut replay = VecDeque::new();
+    loop {
+        let exit = run_socket(&shared, &mut stream, &mut codec, &mut outbound, std::mem::take(&mut replay)).await;
+        // responses will never arrive on a new socket, dropping the senders fails every pending request
+        shared.pending.lock().unwrap().clear();
+        match exit {
+            SocketExit::Closed => {
+                shared.set_state(ConnectionState::Closed);
+                return;
+            }
+            SocketExit::ForcedLogout => {
+                let reason = forced_logout_reason(&mut stream).await;
+                eprintln!("{:?} forced logout: {}", shared.plant, reason);
+                let _ = shared.control.send(ControlEvent::ForcedLogout { plant: shared.plant, reason: reason.clone() });
+                shared.set_state(ConnectionState::LoggedOut { reason });
+                return;
+            }
+            SocketExit::Lost => {}
+        }
+        let (policy, reconnect) = match &reconnect {
+            Some(reconnect) => reconnect,
+            None => {
+                shared.set_state(ConnectionState::Disconnected);
+                return;
+            }
+        };
+
+        // anything queued before the loss is dropped, an order should never be sent late
+        while outbound.try_recv().is_ok() {}
+        codec.clear();
+
+        stream = match reconnect_with_backoff(&shared, policy, reconnect, &mut outbound).await {
+            Ok(session) => {
+                *shared.login.lock().unwrap() = session.info;
+                session.stream
+            }
+            Err(state) => {
+                shared.set_state(state);
+                return;
+            }
+        };
+
+        // written by run_socket ahead of anything sent after the reconnect, within the rate limit
+        let subscriptions = shared.subscriptions.lock().unwrap().replay();
+        for subscription in subscriptions {
+            match shared.codec.encode_message(&subscription) {
+                Ok(frame) => replay.push_back((frame, RequestClass::from_template_id(subscription.template_id()))),
+                Err(e) => eprintln!("Failed to encode {} for replay on {:?}: {}", subscription.name(), shared.plant, e),
+            }
+        }
+        shared.set_state(ConnectionState::Connected);
+    }
+}
+
+/// Try to log in again until an attempt succeeds.
+/// Returns the final state if the policy gives up or the connection is closed while waiting.
+async fn reconnect_with_backoff(
+    shared: &Shared,
+    policy: &ReconnectPolicy,
+    reconnect: &Reconnect,
+    outbound: &mut mpsc::UnboundedReceiver<Outbound>,
+) -> Result<LoginSession, ConnectionState> {
+    let mut attempt = 1;
+    loop {
+        shared.set_state(ConnectionState::Reconnecting { attempt });
+        tokio::select! {
+            _ = tokio::time::sleep(policy.delay(attempt)) => {}
+            request = outbound.recv() => {
+                match request {
+                    // sends are rejected while reconnecting so only a close can arrive here
+                    Some(Outbound::Close) | None => return Err(ConnectionState::Closed),
+                    Some(Outbound::Frame { .. }) => {}
+                }
+            }
+        }
+        match reconnect(shared.plant).await {
+            // reported by the state changing to Connected
+            Ok(session) => return Ok(session),
+            Err(e) => eprintln!("Failed to reconnect {:?}, attempt {}: {}", shared.plant, attempt, e),
+        }
+        if !policy.should_retry(attempt) {
+            return Err(ConnectionState::Disconnected);
+        }
+        attempt += 1;
+    }
+}
+
+/// Owns the socket, writing queued frames and decoding everything received until the socket closes.
+/// The `replay` frames are written first, each waiting for the rate limiter like a queued frame.
+async fn run_socket(
+    shared: &Shared,
+    stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+    codec: &mut RithmicCodec,
+    outbound: &mut mpsc::UnboundedReceiver<Outbound>,
+    mut replay: VecDeque<(Vec<u8>, RequestClass)>,
+) -> SocketExit {
+    let plant = shared.plant;
+    // a queued frame waiting for the rate limiter, nothing queued after it is written until it has been sent
+    let mut held: Option<(Vec<u8>, RequestClass)> = None;
+    let mut ready_at = tokio::time::Instant::now();
+    loop {
+        let mut ready = None;
+        tokio::select! {
+            _ = std::future::ready(()), if held.is_none() && !replay.is_empty() => {
+                ready = replay.pop_front();
+            }
+            _ = tokio::time::sleep_until(ready_at), if held.is_some() => {
+                ready = held.take();
+            }
+            request = outbound.recv(), if held.is_none() && replay.is_empty() => {
+                match request {
+                    Some(Outbound::Frame { frame, class: Some(class) }) => ready = Some((frame, class)),
+                    Some(Outbound::Frame { frame, class: None }) => {
+                        if let Err(e) = stream.send(Message::Binary(frame)).await {
+                            eprintln!("Failed to send to {:?}: {}", plant, e);
+                            return SocketExit::Lost;
+                        }
+                    }
+                    // every handle has been dropped or the connection was closed by us
+                    Some(Outbound::Close) | None => {
+                        let _ = stream.close(None).await;
+                        return SocketExit::Closed;
+                    }
+                }
+            }
+            message = stream.next() => {
+                match message {
+                    Some(Ok(Message::Binary(bytes))) => {
+                        let frames = match codec.decode(&bytes) {
+                            Ok(frames) => frames,
+                            Err(e) => {
+                                eprintln!("Failed to decode frame from {:?}: {}", plant, e);
+                                continue;
+                            }
+                        };
+                        for frame in frames {
+                            match decode_message(&frame) {
+                                // no subscribers is not an error, the message is simply dropped
+                                Ok(message) => {
+                                    shared.route_response(&message);
+                                    if let Some(event) = ControlEvent::from_message(plant, &message) {
+                                        let _ = shared.control.send(event);
+                                    }
+                                    let forced_logout = matches!(message, RithmicMessage::ForcedLogout(_));
+                                    let _ = shared.inbound.send(message);
+                                    if forced_logout {
+                                        return SocketExit::ForcedLogout;
+                                    }
+                                }
+                                Err(e) => eprintln!("Failed to decode message from {:?}: {}", plant, e),
+                            }
+                        }
+                    }
+                    Some(Ok(Message::Close(close))) => {
+                        // received when the market is closed or the server ends the session
+                        eprintln!("{:?} closed: {:?}", plant, close);
+                        return SocketExit::Lost;
+                    }
+                    // pings are answered by tungstenite
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => {
+                        eprintln!("Failed to receive message from {:?}: {}", plant, e);
+                        return SocketExit::Lost;
+                    }
+                    None => return SocketExit::Lost,
+                }
+            }
+        }
+
+        if let Some((frame, class)) = ready {
+            let acquired = match &shared.rate_limiter {
+                Some(rate_limiter) => rate_limiter.try_acquire(class),
+                None => Ok(()),
+            };
+            match acquired {
+                Ok(()) => {
+                    if let Err(e) = stream.send(Message::Binary(frame)).await {
+                        eprintln!("Failed to send to {:?}: {}", plant, e);
+                        return SocketExit::Lost;
+                    }
+                }
+                Err(wait) => match tokio::time::Instant::now().checked_add(wait) {
+                    Some(instant) => {
+                        ready_at = instant;
+                        held = Some((frame, class));
+                    }
+                    // the budget never refills, holding the frame would block everything queued after it
+                    None => {
+                        let error = RithmicApiError::RateLimited { plant, class, retry_after: wait };
+                        eprintln!("Dropped a queued frame for {:?}: {}", plant, error);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Wait briefly for the server to close the socket and use the close frame as the reason, then close our side.
+async fn forced_logout_reason(stream: &mut WebSocketStream<MaybeTlsStream<TcpStream>>) -> String {
+    let mut reason = "Forced logout by the server".to_string();
+    let _ = tokio::time::timeout(FORCED_LOGOUT_CLOSE_TIMEOUT, async {
+        while let Some(Ok(message)) = stream.next().await {
+            if let Message::Close(Some(close)) = message {
+                if !close.reason.is_empty() {
+                    reason = format!("Forced logout by the server: {}", close.reason);
+                }
+                break;
+            }
+        }
+    }).await;
+    let _ = stream.close(None).await;
+    reason
+}
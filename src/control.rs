@@ -0,0 +1,45 @@
+use crate::messages::RithmicMessage;
+use crate::response_code::RithmicResponseCode;
+use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
+use crate::rithmic_proto_objects::rti::{Reject, UserAccountUpdate};
+
+/// How many control events a slow subscriber can fall behind before it starts missing events.
+pub const CONTROL_CHANNEL_CAPACITY: usize = 1_000;
+
+/// Session level messages that every plant can send, published separately from market and order data.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ControlEvent {
+    /// The server logged the user out of the plant, eg: the same user logged in from another machine.
+    /// The connection is closed and is not reconnected.
+    ForcedLogout { plant: SysInfraType, reason: String },
+    /// A request could not be processed, `code` is parsed from the rp_code of the reject.
+    Reject { plant: SysInfraType, code: Option<RithmicResponseCode>, reject: Reject },
+    /// Access to an account was granted, changed or revoked.
+    AccountUpdate { plant: SysInfraType, update: UserAccountUpdate },
+}
+
+impl ControlEvent {
+    /// The control event for a `Reject` (75) or `UserAccountUpdate` (76), forced logouts are published by the connection once it is closed.
+    pub(crate) fn from_message(plant: SysInfraType, message: &RithmicMessage) -> Option<ControlEvent> {
+        match message {
+            RithmicMessage::Reject(reject) => Some(ControlEvent::Reject {
+                plant,
+                code: RithmicResponseCode::parse(&reject.rp_code),
+                reject: reject.clone(),
+            }),
+            RithmicMessage::UserAccountUpdate(update) => Some(ControlEvent::AccountUpdate {
+                plant,
+                update: update.clone(),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn plant(&self) -> SysInfraType {
+        match self {
+            ControlEvent::ForcedLogout { plant, .. }
+            | ControlEvent::Reject { plant, .. }
+            | ControlEvent::AccountUpdate { plant, .. } => *plant,
+        }
+    }
+}
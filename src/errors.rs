@@ -1,7 +1,11 @@
 use thiserror::Error;
 use std::io;
 use tungstenite::Error as WsError;
+use std::time::Duration;
 use prost::DecodeError;
+use crate::rate_limit::RequestClass;
+use crate::response_code::RithmicResponseCode;
+use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
 
 #[derive(Debug, Error)]
 pub enum RithmicApiError {
@@ -14,8 +18,9 @@ pub enum RithmicApiError {
     #[error("IO error occurred: {0}")]
     Io(#[from] io::Error),
 
+    /// Boxed since `WsError` is large and would make every `Result` of this crate large.
     #[error("WebSocket error occurred: {0}")]
-    WebSocket(#[from] WsError),
+    WebSocket(Box<WsError>),
 
     #[error("Protobuf decode error: {0}")]
     ProtobufDecode(#[from] DecodeError),
@@ -30,5 +35,53 @@ pub enum RithmicApiError {
     InvalidServerName(String),
 
     #[error("Invalid config: {0}")]
-    InvalidConfig(String)
+    InvalidConfig(String),
+
+    #[error("Message has no template_id field")]
+    MissingTemplateId,
+
+    #[error("Unknown template_id: {0}")]
+    UnknownTemplateId(i32),
+
+    #[error("Invalid frame: {0}")]
+    InvalidFrame(String),
+
+    #[error("Frame of {length} bytes exceeds the max frame size of {max} bytes")]
+    FrameTooLarge { length: usize, max: usize },
+
+    #[error("Timed out: {operation}")]
+    Timeout { operation: String },
+
+    #[error("Rate limited: {class:?} request on {plant:?}, retry after {retry_after:?}")]
+    RateLimited { plant: SysInfraType, class: RequestClass, retry_after: Duration },
+
+    #[error("Login rejected, code {code}: {text}")]
+    LoginRejected { code: String, text: String },
+
+    #[error("Server rejected template_id {template_id}: {code}")]
+    ServerRejected { template_id: i32, code: RithmicResponseCode },
+
+    #[error("{user} already has a session on {plants:?}")]
+    SessionAlreadyActive { user: String, plants: Vec<SysInfraType> }
+}
+
+impl From<WsError> for RithmicApiError {
+    fn from(error: WsError) -> Self {
+        RithmicApiError::WebSocket(Box::new(error))
+    }
+}
+
+impl RithmicApiError {
+    /// Returns true if the same request may succeed when sent again later, eg: after a reconnect or once the rate limit allows it.
+    /// A request rejected by the server is not retried, the response codes do not say which rejections are temporary.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            RithmicApiError::Io(_)
+                | RithmicApiError::WebSocket(_)
+                | RithmicApiError::Disconnected(_)
+                | RithmicApiError::Timeout { .. }
+                | RithmicApiError::RateLimited { .. }
+        )
+    }
 }
\ No newline at end of file
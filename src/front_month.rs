@@ -0,0 +1,208 @@
+use std::sync::Arc;
+use std::time::Duration;
+use dashmap::DashMap;
+use tokio::sync::{broadcast, watch};
+use crate::connection::{ConnectionState, PlantConnection, WeakPlantConnection};
+use crate::errors::RithmicApiError;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::{FrontMonthContractUpdate, RequestFrontMonthContract, ResponseFrontMonthContract};
+use crate::templates::TemplateId;
+
+/// Number of rollovers a slow receiver can fall behind before it misses some.
+const ROLLOVER_CHANNEL_CAPACITY: usize = 100;
+
+/// The contract currently traded for a product root, eg: `ESZ4` for `ES` on `CME`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FrontMonth {
+    pub root: String,
+    pub exchange: String,
+    pub trading_symbol: String,
+    pub trading_exchange: String,
+    pub symbol_name: Option<String>,
+}
+
+impl FrontMonth {
+    /// None if the message has no root, exchange or trading symbol.
+    fn new(root: &Option<String>, exchange: &Option<String>, trading_symbol: &Option<String>, trading_exchange: &Option<String>, symbol_name: &Option<String>) -> Option<FrontMonth> {
+        let exchange = exchange.clone()?;
+        Some(FrontMonth {
+            root: root.clone()?,
+            trading_symbol: trading_symbol.clone()?,
+            // the contract trades on the root's exchange unless the server says otherwise
+            trading_exchange: trading_exchange.clone().unwrap_or_else(|| exchange.clone()),
+            exchange,
+            symbol_name: symbol_name.clone(),
+        })
+    }
+
+    pub fn from_response(response: &ResponseFrontMonthContract) -> Option<FrontMonth> {
+        FrontMonth::new(&response.symbol, &response.exchange, &response.trading_symbol, &response.trading_exchange, &response.symbol_name)
+    }
+
+    pub fn from_update(update: &FrontMonthContractUpdate) -> Option<FrontMonth> {
+        FrontMonth::new(&update.symbol, &update.exchange, &update.trading_symbol, &update.trading_exchange, &update.symbol_name)
+    }
+}
+
+/// The front month of a product root changed, subscriptions and positions on `previous` should move to `current`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RolloverEvent {
+    pub previous: FrontMonth,
+    pub current: FrontMonth,
+}
+
+struct ResolverState {
+    contracts: DashMap<(String, String), FrontMonth>,
+    rollovers: broadcast::Sender<RolloverEvent>,
+}
+
+/// Resolves product roots to their front month contract with `RequestFrontMonthContract` (113) and keeps every resolved root current
+/// from `FrontMonthContractUpdate` (159), sending a [`RolloverEvent`] when the contract changes.
+/// After a reconnect every resolved root is requested again, since the server does not resend the update request.
+/// Cloning gives another handle to the same contracts.
+#[derive(Clone)]
+pub struct FrontMonthResolver {
+    state: Arc<ResolverState>,
+}
+
+impl Default for FrontMonthResolver {
+    fn default() -> Self {
+        Self {
+            state: Arc::new(ResolverState {
+                contracts: DashMap::new(),
+                rollovers: broadcast::channel(ROLLOVER_CHANNEL_CAPACITY).0,
+            }),
+        }
+    }
+}
+
+impl FrontMonthResolver {
+    /// The front month resolved earlier, without asking the server.
+    pub fn get(&self, root: &str, exchange: &str) -> Option<FrontMonth> {
+        self.state.contracts.get(&(root.to_string(), exchange.to_string())).map(|contract| contract.clone())
+    }
+
+    /// Every resolved root with its current front month.
+    pub fn contracts(&self) -> Vec<FrontMonth> {
+        self.state.contracts.iter().map(|contract| contract.value().clone()).collect()
+    }
+
+    /// Every rollover from now on.
+    pub fn rollovers(&self) -> broadcast::Receiver<RolloverEvent> {
+        self.state.rollovers.subscribe()
+    }
+
+    /// The resolved front month, or request it on the ticker plant connection along with updates for it.
+    pub async fn resolve(&self, connection: &PlantConnection, root: &str, exchange: &str, timeout: Duration) -> Result<FrontMonth, RithmicApiError> {
+        if let Some(contract) = self.get(root, exchange) {
+            return Ok(contract);
+        }
+        match request_front_month(connection, root, exchange, timeout).await? {
+            Some(contract) => {
+                self.apply(contract.clone());
+                Ok(contract)
+            }
+            None => Err(RithmicApiError::ServerErrorDebug(format!("No front month for {} {}", root, exchange)))
+        }
+    }
+
+    /// Store the contract, returns the rollover if a different contract was stored for the root.
+    pub fn apply(&self, contract: FrontMonth) -> Option<RolloverEvent> {
+        let key = (contract.root.clone(), contract.exchange.clone());
+        let previous = self.state.contracts.insert(key, contract.clone())?;
+        if previous.trading_symbol == contract.trading_symbol && previous.trading_exchange == contract.trading_exchange {
+            return None;
+        }
+        let rollover = RolloverEvent { previous, current: contract };
+        // no receivers is not an error
+        let _ = self.state.rollovers.send(rollover.clone());
+        Some(rollover)
+    }
+
+    /// Apply an update for a resolved root, updates for roots that were never resolved are ignored.
+    pub fn apply_update(&self, update: &FrontMonthContractUpdate) -> Option<RolloverEvent> {
+        let contract = FrontMonth::from_update(update)?;
+        if !self.state.contracts.contains_key(&(contract.root.clone(), contract.exchange.clone())) {
+            return None;
+        }
+        self.apply(contract)
+    }
+}
+
+async fn request_front_month(connection: &PlantConnection, root: &str, exchange: &str, timeout: Duration) -> Result<Option<FrontMonth>, RithmicApiError> {
+    let request = RequestFrontMonthContract {
+        template_id: TemplateId::RequestFrontMonthContract.id(),
+        user_msg: vec![],
+        symbol: Some(root.to_string()),
+        exchange: Some(exchange.to_string()),
+        need_updates: Some(true),
+    };
+    let responses = connection.request(request, timeout).await?;
+    Ok(responses.iter().find_map(FrontMonth::from_response))
+}
+
+/// Keep the resolver current with the messages of a ticker plant connection until the connection ends.
+pub(crate) async fn track_front_months(
+    resolver: FrontMonthResolver,
+    connection: WeakPlantConnection,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    mut state: watch::Receiver<ConnectionState>,
+    timeout: Duration,
+) {
+    let mut reconnecting = false;
+    loop {
+        tokio::select! {
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::FrontMonthContractUpdate(update)) => {
+                        resolver.apply_update(&update);
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Front month resolver lagged, skipped {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let current = state.borrow_and_update().clone();
+                match current {
+                    ConnectionState::Reconnecting { .. } => reconnecting = true,
+                    ConnectionState::Connected if reconnecting => {
+                        reconnecting = false;
+                        // the front month may have rolled while the connection was down, requested in a task so updates keep being read meanwhile
+                        tokio::task::spawn(resolve_again(resolver.clone(), connection.clone(), timeout));
+                    }
+                    _ if current.is_terminal() => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Request every resolved root at the same time and apply the answers.
+/// The connection is only held while the requests are waiting for their answers.
+async fn resolve_again(resolver: FrontMonthResolver, connection: WeakPlantConnection, timeout: Duration) {
+    let connection = match connection.upgrade() {
+        Some(connection) => connection,
+        None => return,
+    };
+    let contracts = resolver.contracts();
+    let requests = contracts
+        .iter()
+        .map(|contract| request_front_month(&connection, &contract.root, &contract.exchange, timeout));
+    let results = futures_util::future::join_all(requests).await;
+    for (contract, result) in contracts.iter().zip(results) {
+        match result {
+            Ok(Some(current)) => {
+                resolver.apply(current);
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Failed to request the front month for {} {}: {}", contract.root, contract.exchange, e),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use std::time::{Duration, Instant};
+use futures_util::{SinkExt, StreamExt};
+use tokio_tungstenite::tungstenite::protocol::Message;
+use crate::codec::RithmicCodec;
+use crate::errors::RithmicApiError;
+use crate::rithmic_proto_objects::rti::RequestHeartbeat;
+use crate::templates::TemplateId;
+use crate::timeouts::{connect_websocket, Timeouts};
+
+/// A gateway listed in `ResponseRithmicSystemGatewayInfo` (21).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Gateway {
+    pub name: String,
+    pub uri: String,
+}
+
+/// Which gateway the client connects to.
+#[derive(Clone, Debug)]
+pub struct GatewayConfig {
+    /// Always connect to this uri, the gateway list and `servers.toml` are ignored.
+    pub override_uri: Option<String>,
+    /// Probe the gateways of the system before the first login and connect to the fastest one.
+    /// If no gateway is reachable the uri from `servers.toml` is used.
+    pub auto_select: bool,
+    /// How long each step of a probe may take before the gateway is treated as unreachable.
+    pub probe_timeout: Duration,
+}
+
+impl Default for GatewayConfig {
+    fn default() -> Self {
+        Self {
+            override_uri: None,
+            auto_select: false,
+            probe_timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// The latency measured for a gateway.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GatewayProbe {
+    pub gateway: Gateway,
+    /// Time to open the WebSocket, including the TLS handshake.
+    pub connect_latency: Option<Duration>,
+    /// Round trip time of a `RequestHeartbeat` on the open socket.
+    pub heartbeat_latency: Option<Duration>,
+    /// Why the probe failed, None if the gateway is reachable.
+    pub error: Option<String>,
+}
+
+impl GatewayProbe {
+    pub fn is_reachable(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Connect plus heartbeat latency, None if the gateway is unreachable.
+    pub fn total_latency(&self) -> Option<Duration> {
+        match (self.connect_latency, self.heartbeat_latency, &self.error) {
+            (Some(connect), Some(heartbeat), None) => Some(connect + heartbeat),
+            _ => None,
+        }
+    }
+}
+
+/// The reachable gateway with the lowest total latency.
+pub fn fastest(probes: &[GatewayProbe]) -> Option<&GatewayProbe> {
+    probes
+        .iter()
+        .filter_map(|probe| probe.total_latency().map(|latency| (latency, probe)))
+        .min_by_key(|(latency, _)| *latency)
+        .map(|(_, probe)| probe)
+}
+
+/// Open a socket to the gateway and time the connection and one heartbeat round trip, the socket is closed afterwards.
+/// Any answer counts as the heartbeat response, since the server may reject heartbeats before login.
+pub(crate) async fn probe_gateway(gateway: Gateway, codec: RithmicCodec, timeout: Duration) -> GatewayProbe {
+    let mut probe = GatewayProbe {
+        gateway,
+        connect_latency: None,
+        heartbeat_latency: None,
+        error: None,
+    };
+
+    let timeouts = Timeouts {
+        connect: timeout,
+        handshake: timeout,
+        ..Timeouts::default()
+    };
+    let start = Instant::now();
+    let mut stream = match connect_websocket(&probe.gateway.uri, &timeouts).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            probe.error = Some(format!("Failed to connect: {}", e));
+            return probe;
+        }
+    };
+    probe.connect_latency = Some(start.elapsed());
+
+    let heartbeat = RequestHeartbeat {
+        template_id: TemplateId::RequestHeartbeat.id(),
+        user_msg: vec![],
+        ssboe: None,
+        usecs: None,
+    };
+    let result: Result<Duration, RithmicApiError> = async {
+        let frame = codec.encode(&heartbeat)?;
+        let start = Instant::now();
+        stream.send(Message::Binary(frame)).await?;
+        loop {
+            match tokio::time::timeout(timeout, stream.next()).await {
+                Ok(Some(Ok(Message::Binary(_)))) => return Ok(start.elapsed()),
+                Ok(Some(Ok(_))) => {}
+                Ok(Some(Err(e))) => return Err(e.into()),
+                Ok(None) => return Err(RithmicApiError::Disconnected("Gateway closed the connection".to_string())),
+                Err(_) => return Err(RithmicApiError::Timeout { operation: "gateway heartbeat".to_string() }),
+            }
+        }
+    }.await;
+    match result {
+        Ok(latency) => probe.heartbeat_latency = Some(latency),
+        Err(e) => probe.error = Some(e.to_string()),
+    }
+    let _ = stream.close(None).await;
+    probe
+}
@@ -0,0 +1,130 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, watch};
+use crate::connection::{ConnectionState, WeakPlantConnection};
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::RequestHeartbeat;
+use crate::templates::TemplateId;
+
+/// Used when the login response does not include a heartbeat interval.
+pub const DEFAULT_HEARTBEAT_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Settings for the managed heartbeat of each plant connection.
+#[derive(Clone, Debug)]
+pub struct HeartbeatConfig {
+    /// Send heartbeats automatically for connections created with `connect_plant`.
+    pub enabled: bool,
+    /// Use this interval instead of the `heartbeat_interval` returned in the login response.
+    pub interval_override: Option<Duration>,
+    /// How many intervals an unanswered heartbeat can wait before the plant is reported as stale.
+    pub stale_after_intervals: u32,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_override: None,
+            stale_after_intervals: 2,
+        }
+    }
+}
+
+/// The heartbeat state of a plant, updated by the heartbeat task.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeartbeatStatus {
+    /// When the last heartbeat request was sent, None if other traffic has kept the connection alive.
+    pub last_sent: Option<Instant>,
+    /// When the last `ResponseHeartbeat` arrived.
+    pub last_response: Option<Instant>,
+    /// Round trip time of the last answered heartbeat.
+    pub last_round_trip: Option<Duration>,
+    /// True when a heartbeat has gone unanswered for longer than the stale timeout.
+    pub is_stale: bool,
+}
+
+/// Sends `RequestHeartbeat` (18) whenever nothing else has been sent for an interval, and matches each `ResponseHeartbeat` (19) to the oldest unanswered request.
+/// Pauses while the plant is reconnecting and stops once the connection is closed or disconnected for good, or every handle to it is dropped.
+pub(crate) async fn run_heartbeat(
+    connection: WeakPlantConnection,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    state: watch::Receiver<ConnectionState>,
+    interval: Duration,
+    stale_after_intervals: u32,
+) {
+    // check several times per interval so a heartbeat always goes out before the interval expires
+    let check_period = (interval / 4).max(Duration::from_millis(250));
+    let stale_after = interval * stale_after_intervals.max(1);
+    let mut ticker = tokio::time::interval(check_period);
+    let mut awaiting: VecDeque<Instant> = VecDeque::new();
+
+    while !state.borrow().is_terminal() {
+        tokio::select! {
+            _ = ticker.tick() => {
+                // the socket task closes the connection once every handle is dropped
+                let connection = match connection.upgrade() {
+                    Some(connection) => connection,
+                    None => break,
+                };
+                let now = Instant::now();
+                if !connection.is_connected() {
+                    // heartbeats sent before a reconnect will never be answered
+                    awaiting.clear();
+                    continue;
+                }
+                if now.duration_since(connection.last_sent()) + check_period >= interval {
+                    let (ssboe, usecs) = timestamp();
+                    let request = RequestHeartbeat {
+                        template_id: TemplateId::RequestHeartbeat.id(),
+                        user_msg: vec![],
+                        ssboe: Some(ssboe),
+                        usecs: Some(usecs),
+                    };
+                    // fails if the connection was lost since the check above, the next tick will see the new state
+                    if connection.send(&request).await.is_err() {
+                        continue;
+                    }
+                    awaiting.push_back(now);
+                    connection.update_heartbeat_status(|status| status.last_sent = Some(now));
+                }
+                let is_stale = match awaiting.front() {
+                    Some(sent) => now.duration_since(*sent) > stale_after,
+                    None => false,
+                };
+                connection.update_heartbeat_status(|status| {
+                    if is_stale && !status.is_stale {
+                        eprintln!("{:?} is stale, no heartbeat response for {:?}", connection.plant(), stale_after);
+                    }
+                    status.is_stale = is_stale;
+                });
+            }
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::ResponseHeartbeat(_)) => {
+                        let connection = match connection.upgrade() {
+                            Some(connection) => connection,
+                            None => break,
+                        };
+                        let now = Instant::now();
+                        let round_trip = awaiting.pop_front().map(|sent| now.duration_since(sent));
+                        connection.update_heartbeat_status(|status| {
+                            status.last_response = Some(now);
+                            if round_trip.is_some() {
+                                status.last_round_trip = round_trip;
+                            }
+                            status.is_stale = false;
+                        });
+                    }
+                    Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        }
+    }
+}
+
+/// Seconds and microseconds since the unix epoch, as used by the ssboe and usecs fields.
+fn timestamp() -> (i32, i32) {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+    (now.as_secs() as i32, now.subsec_micros() as i32)
+}
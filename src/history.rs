@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use crate::connection::PlantConnection;
+use crate::errors::RithmicApiError;
+use crate::rithmic_proto_objects::rti::request_time_bar_replay::{BarType, Direction, TimeOrder};
+use crate::rithmic_proto_objects::rti::{RequestResumeBars, RequestTimeBarReplay, ResponseTimeBarReplay};
+use crate::templates::TemplateId;
+
+/// A time bar from `ResponseTimeBarReplay` (203).
+#[derive(Clone, Debug, PartialEq)]
+pub struct TimeBar {
+    pub symbol: String,
+    pub exchange: String,
+    /// The time of the bar in seconds since the unix epoch.
+    pub marker: i32,
+    pub period: Option<String>,
+    pub open: Option<f64>,
+    pub high: Option<f64>,
+    pub low: Option<f64>,
+    pub close: Option<f64>,
+    pub volume: Option<u64>,
+    pub bid_volume: Option<u64>,
+    pub ask_volume: Option<u64>,
+    pub num_trades: Option<u64>,
+    pub settlement_price: Option<f64>,
+}
+
+impl TimeBar {
+    /// None if the response is the final row, which has no bar.
+    pub fn from_response(response: &ResponseTimeBarReplay) -> Option<TimeBar> {
+        Some(TimeBar {
+            symbol: response.symbol.clone()?,
+            exchange: response.exchange.clone()?,
+            marker: response.marker?,
+            period: response.period.clone(),
+            open: response.open_price,
+            high: response.high_price,
+            low: response.low_price,
+            close: response.close_price,
+            volume: response.volume,
+            bid_volume: response.bid_volume,
+            ask_volume: response.ask_volume,
+            num_trades: response.num_trades,
+            settlement_price: match response.has_settlement_price {
+                Some(true) => response.settlement_price,
+                _ => None,
+            },
+        })
+    }
+}
+
+/// The bars to download with [`download_time_bars`].
+#[derive(Clone, Debug)]
+pub struct TimeBarDownload {
+    pub symbol: String,
+    pub exchange: String,
+    pub bar_type: BarType,
+    pub period: i32,
+    /// Seconds since the unix epoch, inclusive.
+    pub start: i32,
+    /// Seconds since the unix epoch, inclusive.
+    pub end: i32,
+    /// The range is requested in chunks of at most this many bars.
+    pub chunk_bars: i32,
+    /// The server stops a replay after this many bars, a chunk that reaches it is continued with `RequestResumeBars` (210).
+    pub max_bars_per_response: usize,
+}
+
+impl TimeBarDownload {
+    pub fn new(symbol: String, exchange: String, bar_type: BarType, period: i32, start: i32, end: i32) -> Self {
+        Self {
+            symbol,
+            exchange,
+            bar_type,
+            period,
+            start,
+            end,
+            chunk_bars: 10_000,
+            max_bars_per_response: 10_000,
+        }
+    }
+
+    /// The length of one bar in seconds.
+    pub fn bar_seconds(&self) -> i64 {
+        let unit = match self.bar_type {
+            BarType::SecondBar => 1,
+            BarType::MinuteBar => 60,
+            BarType::DailyBar => 86_400,
+            BarType::WeeklyBar => 604_800,
+        };
+        unit * self.period.max(1) as i64
+    }
+
+    /// The (start, end) of each chunk, the end of a chunk is the start of the next so no bar falls between them.
+    pub fn chunks(&self) -> Vec<(i32, i32)> {
+        let length = (self.bar_seconds() * self.chunk_bars.max(1) as i64).min(i32::MAX as i64) as i32;
+        let mut chunks = Vec::new();
+        let mut start = self.start;
+        while start <= self.end {
+            let end = start.saturating_add(length).min(self.end);
+            chunks.push((start, end));
+            if end == self.end {
+                break;
+            }
+            start = end;
+        }
+        chunks
+    }
+}
+
+/// How far a download has got, reported after every response.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DownloadProgress {
+    pub chunks_done: usize,
+    pub chunks_total: usize,
+    /// Distinct bars received so far.
+    pub bars: usize,
+    /// The time of the last bar received.
+    pub last_marker: Option<i32>,
+}
+
+/// Download the bars of a range on a history plant connection, sorted by time with overlapping bars removed.
+/// The range is split into chunks, a chunk that is cut off by the server is continued with `RequestResumeBars`,
+/// or requested again from its last bar if the server gave no request key.
+pub async fn download_time_bars(
+    connection: &PlantConnection,
+    download: &TimeBarDownload,
+    timeout: Duration,
+    mut progress: impl FnMut(&DownloadProgress),
+) -> Result<Vec<TimeBar>, RithmicApiError> {
+    let chunks = download.chunks();
+    let mut bars: BTreeMap<i32, TimeBar> = BTreeMap::new();
+    let mut report = DownloadProgress {
+        chunks_done: 0,
+        chunks_total: chunks.len(),
+        bars: 0,
+        last_marker: None,
+    };
+
+    for (chunk_start, chunk_end) in chunks {
+        let mut start = chunk_start;
+        loop {
+            let responses = replay_time_bars(connection, download, start, chunk_end, timeout).await?;
+            // the final row has no bar
+            let mut received = responses.iter().filter_map(TimeBar::from_response).count();
+            let request_key = responses.iter().find_map(|response| response.request_key.clone());
+            add_bars(&mut bars, &responses, &mut report);
+            progress(&report);
+
+            if let Some(request_key) = &request_key {
+                while received >= download.max_bars_per_response {
+                    let responses = resume_time_bars(connection, request_key, timeout).await?;
+                    received = responses.iter().filter_map(TimeBar::from_response).count();
+                    add_bars(&mut bars, &responses, &mut report);
+                    progress(&report);
+                }
+            }
+            // no request key to resume with, ask again from the last bar
+            match report.last_marker {
+                Some(last_marker) if received >= download.max_bars_per_response && last_marker > start && last_marker < chunk_end => start = last_marker,
+                _ => break,
+            }
+        }
+        report.chunks_done += 1;
+        progress(&report);
+    }
+    Ok(bars.into_values().collect())
+}
+
+fn add_bars(bars: &mut BTreeMap<i32, TimeBar>, responses: &[ResponseTimeBarReplay], report: &mut DownloadProgress) {
+    for bar in responses.iter().filter_map(TimeBar::from_response) {
+        report.last_marker = report.last_marker.max(Some(bar.marker));
+        bars.insert(bar.marker, bar);
+    }
+    report.bars = bars.len();
+}
+
+async fn replay_time_bars(connection: &PlantConnection, download: &TimeBarDownload, start: i32, end: i32, timeout: Duration) -> Result<Vec<ResponseTimeBarReplay>, RithmicApiError> {
+    let request = RequestTimeBarReplay {
+        template_id: TemplateId::RequestTimeBarReplay.id(),
+        user_msg: vec![],
+        symbol: Some(download.symbol.clone()),
+        exchange: Some(download.exchange.clone()),
+        bar_type: Some(download.bar_type as i32),
+        bar_type_period: Some(download.period),
+        start_index: Some(start),
+        finish_index: Some(end),
+        user_max_count: None,
+        direction: Some(Direction::First as i32),
+        time_order: Some(TimeOrder::Forwards as i32),
+        resume_bars: Some(true),
+    };
+    connection.request(request, timeout).await
+}
+
+/// Continue a replay that was cut off, the rows carry the request key of the replay and are matched by it.
+async fn resume_time_bars(connection: &PlantConnection, request_key: &str, timeout: Duration) -> Result<Vec<ResponseTimeBarReplay>, RithmicApiError> {
+    let request = RequestResumeBars {
+        template_id: TemplateId::RequestResumeBars.id(),
+        user_msg: vec![],
+        request_key: Some(request_key.to_string()),
+    };
+    let responses = connection.request_keyed(request, request_key, timeout).await?;
+    Ok(responses
+        .into_iter()
+        .filter_map(|message| ResponseTimeBarReplay::try_from(message).ok())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn download(bar_type: BarType, period: i32, start: i32, end: i32, chunk_bars: i32) -> TimeBarDownload {
+        let mut download = TimeBarDownload::new("ESZ4".to_string(), "CME".to_string(), bar_type, period, start, end);
+        download.chunk_bars = chunk_bars;
+        download
+    }
+
+    fn row(marker: Option<i32>, close: f64) -> ResponseTimeBarReplay {
+        ResponseTimeBarReplay {
+            template_id: TemplateId::ResponseTimeBarReplay.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            marker,
+            close_price: Some(close),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn bar_seconds_multiplies_the_period() {
+        assert_eq!(download(BarType::SecondBar, 15, 0, 0, 1).bar_seconds(), 15);
+        assert_eq!(download(BarType::MinuteBar, 5, 0, 0, 1).bar_seconds(), 300);
+        assert_eq!(download(BarType::DailyBar, 1, 0, 0, 1).bar_seconds(), 86_400);
+        assert_eq!(download(BarType::WeeklyBar, 0, 0, 0, 1).bar_seconds(), 604_800);
+    }
+
+    #[test]
+    fn chunks_share_their_boundaries() {
+        let chunks = download(BarType::MinuteBar, 1, 0, 150, 1).chunks();
+        assert_eq!(chunks, vec![(0, 60), (60, 120), (120, 150)]);
+    }
+
+    #[test]
+    fn chunks_end_exactly_on_the_range_end() {
+        let chunks = download(BarType::MinuteBar, 1, 0, 120, 1).chunks();
+        assert_eq!(chunks, vec![(0, 60), (60, 120)]);
+    }
+
+    #[test]
+    fn chunks_of_a_short_range() {
+        assert_eq!(download(BarType::MinuteBar, 1, 100, 100, 10).chunks(), vec![(100, 100)]);
+        assert_eq!(download(BarType::MinuteBar, 1, 100, 130, 10).chunks(), vec![(100, 130)]);
+        assert!(download(BarType::MinuteBar, 1, 100, 99, 10).chunks().is_empty());
+    }
+
+    #[test]
+    fn chunks_do_not_overflow_near_the_end_of_time() {
+        let chunks = download(BarType::WeeklyBar, 1, i32::MAX - 10, i32::MAX, i32::MAX).chunks();
+        assert_eq!(chunks, vec![(i32::MAX - 10, i32::MAX)]);
+        let chunks = download(BarType::DailyBar, 1, 0, i32::MAX, 0).chunks();
+        assert_eq!(chunks.first(), Some(&(0, 86_400)));
+        assert_eq!(chunks.last().map(|chunk| chunk.1), Some(i32::MAX));
+    }
+
+    #[test]
+    fn add_bars_skips_the_final_row_and_keeps_the_latest_copy_of_a_bar() {
+        let mut bars = BTreeMap::new();
+        let mut report = DownloadProgress { chunks_done: 0, chunks_total: 2, bars: 0, last_marker: None };
+        add_bars(&mut bars, &[row(Some(60), 1.0), row(Some(120), 2.0), row(None, 0.0)], &mut report);
+        assert_eq!(report.bars, 2);
+        assert_eq!(report.last_marker, Some(120));
+
+        // the next chunk starts on the boundary bar
+        add_bars(&mut bars, &[row(Some(120), 2.5), row(Some(180), 3.0)], &mut report);
+        assert_eq!(report.bars, 3);
+        assert_eq!(report.last_marker, Some(180));
+        assert_eq!(bars.get(&120).and_then(|bar| bar.close), Some(2.5));
+    }
+
+    #[test]
+    fn from_response_keeps_the_settlement_price_only_when_flagged() {
+        let mut response = row(Some(60), 1.0);
+        response.settlement_price = Some(5.0);
+        assert_eq!(TimeBar::from_response(&response).and_then(|bar| bar.settlement_price), None);
+        response.has_settlement_price = Some(true);
+        assert_eq!(TimeBar::from_response(&response).and_then(|bar| bar.settlement_price), Some(5.0));
+        assert!(TimeBar::from_response(&row(None, 1.0)).is_none());
+    }
+}
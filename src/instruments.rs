@@ -0,0 +1,330 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use crate::connection::PlantConnection;
+use crate::errors::RithmicApiError;
+use crate::rithmic_proto_objects::rti::request_search_symbols::{InstrumentType, Pattern};
+use crate::rithmic_proto_objects::rti::{RequestReferenceData, RequestSearchSymbols, ResponseGetInstrumentByUnderlying, ResponseReferenceData, ResponseSearchSymbols};
+use crate::templates::TemplateId;
+
+/// The contract details of a symbol.
+/// Instruments from [`InstrumentCache::fetch`] come from `ResponseReferenceData` (15) and have every field the server knows,
+/// instruments of an options chain come from `ResponseGetInstrumentByUnderlying` (103) and have the contract details but no tradable flag,
+/// instruments from [`InstrumentCache::search`] come from `ResponseSearchSymbols` (110) and only have the name, product code, type and expiration.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Instrument {
+    pub symbol: String,
+    pub exchange: String,
+    pub symbol_name: Option<String>,
+    pub exchange_symbol: Option<String>,
+    pub trading_symbol: Option<String>,
+    pub trading_exchange: Option<String>,
+    pub product_code: Option<String>,
+    /// As sent by the server, eg: "Future" or "Future Option".
+    pub instrument_type: Option<String>,
+    pub underlying_symbol: Option<String>,
+    /// CCYYMMDD
+    pub expiration_date: Option<String>,
+    pub currency: Option<String>,
+    /// The minimum quoted price change.
+    pub tick_size: Option<f64>,
+    /// The value of one full point, in `currency`.
+    pub point_value: Option<f64>,
+    pub strike_price: Option<f64>,
+    pub put_call_indicator: Option<String>,
+    pub is_tradable: Option<bool>,
+}
+
+impl Instrument {
+    /// None if the response has no symbol or exchange.
+    pub fn from_reference_data(response: &ResponseReferenceData) -> Option<Instrument> {
+        Some(Instrument {
+            symbol: response.symbol.clone()?,
+            exchange: response.exchange.clone()?,
+            symbol_name: response.symbol_name.clone(),
+            exchange_symbol: response.exchange_symbol.clone(),
+            trading_symbol: response.trading_symbol.clone(),
+            trading_exchange: response.trading_exchange.clone(),
+            product_code: response.product_code.clone(),
+            instrument_type: response.instrument_type.clone(),
+            underlying_symbol: response.underlying_symbol.clone(),
+            expiration_date: response.expiration_date.clone(),
+            currency: response.currency.clone(),
+            tick_size: response.min_qprice_change.or(response.min_fprice_change),
+            point_value: response.single_point_value,
+            strike_price: response.strike_price,
+            put_call_indicator: response.put_call_indicator.clone(),
+            is_tradable: response.is_tradable.as_deref().map(|is_tradable| is_tradable.eq_ignore_ascii_case("true")),
+        })
+    }
+
+    /// None if the response is the final row, which has no symbol.
+    pub fn from_underlying(response: &ResponseGetInstrumentByUnderlying) -> Option<Instrument> {
+        Some(Instrument {
+            symbol: response.symbol.clone()?,
+            exchange: response.exchange.clone()?,
+            symbol_name: response.symbol_name.clone(),
+            exchange_symbol: response.exchange_symbol.clone(),
+            product_code: response.product_code.clone(),
+            instrument_type: response.instrument_type.clone(),
+            underlying_symbol: response.underlying_symbol.clone(),
+            expiration_date: response.expiration_date.clone(),
+            currency: response.currency.clone(),
+            tick_size: response.min_qprice_change.or(response.min_fprice_change),
+            point_value: response.single_point_value,
+            strike_price: response.strike_price,
+            put_call_indicator: response.put_call_indicator.clone(),
+            ..Instrument::default()
+        })
+    }
+
+    /// None if the response is the final row, which has no symbol.
+    pub fn from_search(response: &ResponseSearchSymbols) -> Option<Instrument> {
+        Some(Instrument {
+            symbol: response.symbol.clone()?,
+            exchange: response.exchange.clone()?,
+            symbol_name: response.symbol_name.clone(),
+            product_code: response.product_code.clone(),
+            instrument_type: response.instrument_type.clone(),
+            expiration_date: response.expiration_date.clone(),
+            ..Instrument::default()
+        })
+    }
+
+    /// The value of one tick, in `currency`.
+    pub fn tick_value(&self) -> Option<f64> {
+        match (self.tick_size, self.point_value) {
+            (Some(tick_size), Some(point_value)) => Some(tick_size * point_value),
+            _ => None,
+        }
+    }
+
+    /// Round a price to the nearest tick, unchanged if the tick size is unknown.
+    pub fn round_to_tick(&self, price: f64) -> f64 {
+        match self.tick_size {
+            Some(tick_size) if tick_size > 0.0 => (price / tick_size).round() * tick_size,
+            _ => price,
+        }
+    }
+}
+
+/// What to search for, used for `RequestSearchSymbols` (109) and for [`InstrumentCache::find`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct InstrumentSearch {
+    pub text: String,
+    pub pattern: Pattern,
+    pub exchange: Option<String>,
+    pub product_code: Option<String>,
+    pub instrument_type: Option<InstrumentType>,
+}
+
+impl InstrumentSearch {
+    /// Symbols or names containing the text, on any exchange.
+    pub fn new(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            pattern: Pattern::Contains,
+            exchange: None,
+            product_code: None,
+            instrument_type: None,
+        }
+    }
+
+    /// Returns true if the instrument matches, text is compared with the symbol and name ignoring case.
+    pub fn matches(&self, instrument: &Instrument) -> bool {
+        let text = self.text.to_lowercase();
+        let text_matches = [Some(&instrument.symbol), instrument.symbol_name.as_ref()]
+            .into_iter()
+            .flatten()
+            .any(|value| {
+                let value = value.to_lowercase();
+                match self.pattern {
+                    Pattern::Equals => value == text,
+                    Pattern::Contains => value.contains(&text),
+                }
+            });
+        text_matches
+            && self.exchange.as_ref().is_none_or(|exchange| *exchange == instrument.exchange)
+            && self.product_code.as_ref().is_none_or(|product_code| Some(product_code) == instrument.product_code.as_ref())
+            && self.instrument_type.is_none_or(|instrument_type| {
+                instrument.instrument_type.as_deref().is_some_and(|value| normalise(value) == normalise(instrument_type.as_str_name()))
+            })
+    }
+}
+
+/// "Future Option" and "FUTURE_OPTION" both give "futureoption".
+fn normalise(value: &str) -> String {
+    value.chars().filter(|c| c.is_ascii_alphanumeric()).map(|c| c.to_ascii_lowercase()).collect()
+}
+
+/// The file format, a list since TOML keys can not be tuples.
+#[derive(Serialize, Deserialize, Default)]
+struct InstrumentFile {
+    instruments: Vec<Instrument>,
+}
+
+/// Reference data by (symbol, exchange), fetched from the ticker plant the first time a symbol is asked for.
+/// If a file is set the cache is loaded from it when created, and only [`InstrumentCache::save`] writes it,
+/// so warm the cache with any number of fetches, inserts or option chains and save once to keep the reference data between runs.
+/// Cloning the cache gives another handle to the same instruments.
+#[derive(Clone, Default)]
+pub struct InstrumentCache {
+    instruments: Arc<DashMap<(String, String), Instrument>>,
+    file: Option<PathBuf>,
+    /// Held while the file is written, saves share the temporary file.
+    saving: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl InstrumentCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A cache kept in a TOML file, the file is read now if it exists.
+    pub fn with_file(file: impl AsRef<Path>) -> Result<Self, RithmicApiError> {
+        let cache = Self {
+            instruments: Arc::new(DashMap::new()),
+            file: Some(file.as_ref().to_path_buf()),
+            saving: Arc::default(),
+        };
+        let toml_string = match fs::read_to_string(file.as_ref()) {
+            Ok(toml_string) => toml_string,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(cache),
+            Err(e) => return Err(e.into()),
+        };
+        let instrument_file: InstrumentFile = toml::from_str(&toml_string)?;
+        for instrument in instrument_file.instruments {
+            cache.insert(instrument);
+        }
+        Ok(cache)
+    }
+
+    /// The cached instrument, without asking the server.
+    pub fn get(&self, symbol: &str, exchange: &str) -> Option<Instrument> {
+        self.instruments.get(&(symbol.to_string(), exchange.to_string())).map(|instrument| instrument.clone())
+    }
+
+    pub fn insert(&self, instrument: Instrument) {
+        self.instruments.insert((instrument.symbol.clone(), instrument.exchange.clone()), instrument);
+    }
+
+    pub fn instruments(&self) -> Vec<Instrument> {
+        self.instruments.iter().map(|instrument| instrument.value().clone()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+
+    /// The cached instruments that match the search.
+    pub fn find(&self, search: &InstrumentSearch) -> Vec<Instrument> {
+        self.instruments
+            .iter()
+            .filter(|instrument| search.matches(instrument.value()))
+            .map(|instrument| instrument.value().clone())
+            .collect()
+    }
+
+    /// The cached instrument, or request the reference data on the ticker plant connection and cache it.
+    /// The file is not written, call [`InstrumentCache::save`] to persist it.
+    pub async fn fetch(&self, connection: &PlantConnection, symbol: &str, exchange: &str, timeout: Duration) -> Result<Instrument, RithmicApiError> {
+        if let Some(instrument) = self.get(symbol, exchange) {
+            return Ok(instrument);
+        }
+        let request = RequestReferenceData {
+            template_id: TemplateId::RequestReferenceData.id(),
+            user_msg: vec![],
+            symbol: Some(symbol.to_string()),
+            exchange: Some(exchange.to_string()),
+        };
+        let instrument = connection
+            .request(request, timeout)
+            .await?
+            .iter()
+            .find_map(Instrument::from_reference_data);
+        let instrument = match instrument {
+            Some(instrument) => instrument,
+            None => return Err(RithmicApiError::ServerErrorDebug(format!("No reference data for {} {}", symbol, exchange)))
+        };
+        self.insert(instrument.clone());
+        Ok(instrument)
+    }
+
+    /// Search the symbols on the ticker plant connection, the results are not cached since they have no reference data.
+    pub async fn search(&self, connection: &PlantConnection, search: &InstrumentSearch, timeout: Duration) -> Result<Vec<Instrument>, RithmicApiError> {
+        let request = RequestSearchSymbols {
+            template_id: TemplateId::RequestSearchSymbols.id(),
+            user_msg: vec![],
+            search_text: Some(search.text.clone()),
+            exchange: search.exchange.clone(),
+            product_code: search.product_code.clone(),
+            instrument_type: search.instrument_type.map(|instrument_type| instrument_type as i32),
+            pattern: Some(search.pattern as i32),
+        };
+        let responses = connection.request(request, timeout).await?;
+        Ok(responses.iter().filter_map(Instrument::from_search).collect())
+    }
+
+    /// Write every cached instrument to the file, does nothing if the cache has no file.
+    /// The file is written next to the old one and renamed over it, so a crash while writing never leaves a partly written file.
+    pub async fn save(&self) -> Result<(), RithmicApiError> {
+        let file = match &self.file {
+            Some(file) => file.clone(),
+            None => return Ok(()),
+        };
+        let _saving = self.saving.lock().await;
+        let mut instruments = self.instruments();
+        instruments.sort_by(|a, b| (&a.symbol, &a.exchange).cmp(&(&b.symbol, &b.exchange)));
+        let toml_string = toml::to_string(&InstrumentFile { instruments })
+            .map_err(|e| RithmicApiError::ClientErrorDebug(format!("Failed to serialize instruments: {}", e)))?;
+        let mut temp_file = file.clone().into_os_string();
+        temp_file.push(".tmp");
+        let temp_file = PathBuf::from(temp_file);
+        tokio::task::spawn_blocking(move || {
+            fs::write(&temp_file, toml_string)?;
+            fs::rename(&temp_file, &file)
+        })
+        .await
+        .map_err(|e| RithmicApiError::ClientErrorDebug(format!("Failed to save instruments: {}", e)))??;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instrument(symbol: &str) -> Instrument {
+        Instrument {
+            symbol: symbol.to_string(),
+            exchange: "CME".to_string(),
+            tick_size: Some(0.25),
+            point_value: Some(50.0),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn the_file_is_only_written_by_save() {
+        let file = std::env::temp_dir().join(format!("ff_rithmic_api_instruments_{}.toml", std::process::id()));
+        let _ = fs::remove_file(&file);
+        let cache = InstrumentCache::with_file(&file).unwrap();
+        cache.insert(instrument("ESZ4"));
+        cache.insert(instrument("NQZ4"));
+        assert!(!file.exists());
+
+        cache.save().await.unwrap();
+        let loaded = InstrumentCache::with_file(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded.get("ESZ4", "CME"), Some(instrument("ESZ4")));
+    }
+}
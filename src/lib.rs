@@ -1,8 +1,33 @@
 pub mod rithmic_proto_objects;
+pub mod accounts;
 pub mod api_client;
+pub mod codec;
+pub mod config;
+pub mod connection;
+pub mod control;
 pub mod credentials;
 pub mod examples;
+pub mod front_month;
+pub mod gateways;
+pub mod heartbeat;
+pub mod history;
+pub mod instruments;
 pub mod errors;
+pub mod manager;
+pub mod market_by_order;
+pub mod messages;
+pub mod options;
+pub mod price_level_book;
+pub mod quotes;
+pub mod rate_limit;
+pub mod reconnect;
+pub mod request;
+pub mod response_code;
 pub mod servers;
+pub mod session;
+pub mod subscriptions;
 pub mod systems;
+pub mod templates;
+pub mod timeouts;
+pub mod volume_profile;
 
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt};
+use crate::api_client::RithmicApiClient;
+use crate::errors::RithmicApiError;
+use crate::messages::RithmicMessage;
+use crate::request::RithmicRequest;
+use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
+use crate::rithmic_proto_objects::rti::RequestAccountList;
+use crate::session::RithmicSession;
+use crate::systems::RithmicSystem;
+use crate::templates::TemplateId;
+
+/// Identifies one set of credentials, rithmic allows one session per user per plant on a system.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CredentialKey {
+    pub system: RithmicSystem,
+    pub user: String,
+}
+
+/// An account or PnL message from one of the managed sessions.
+#[derive(Clone, Debug)]
+pub struct TaggedMessage {
+    pub key: CredentialKey,
+    pub plant: SysInfraType,
+    pub message: RithmicMessage,
+}
+
+/// Holds a [`RithmicApiClient`] for each broker or prop firm login and routes requests to the client that owns an account.
+/// Only one client can be added per user and system, and each client only logs in to a plant once, so a login never forces out another session of the same user.
+#[derive(Default)]
+pub struct ConnectionManager {
+    clients: DashMap<CredentialKey, Arc<RithmicApiClient>>,
+    sessions: DashMap<CredentialKey, Arc<RithmicSession>>,
+    /// Held by [`ConnectionManager::connect`] from the session check until the session is stored, so parallel calls for the same credentials log in once.
+    logins: DashMap<CredentialKey, Arc<tokio::sync::Mutex<()>>>,
+}
+
+impl ConnectionManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a client, fails if a client for the same system and user was already added.
+    pub fn add_client(&self, client: Arc<RithmicApiClient>) -> Result<CredentialKey, RithmicApiError> {
+        let key = CredentialKey {
+            system: client.credentials().system_name.clone(),
+            user: client.credentials().user.clone(),
+        };
+        match self.clients.entry(key.clone()) {
+            Entry::Occupied(_) => Err(RithmicApiError::ClientErrorDebug(format!("A client for {} on {} was already added", key.user, key.system))),
+            Entry::Vacant(entry) => {
+                entry.insert(client);
+                Ok(key)
+            }
+        }
+    }
+
+    pub fn client(&self, key: &CredentialKey) -> Option<Arc<RithmicApiClient>> {
+        self.clients.get(key).map(|client| client.clone())
+    }
+
+    pub fn keys(&self) -> Vec<CredentialKey> {
+        self.clients.iter().map(|client| client.key().clone()).collect()
+    }
+
+    pub fn session(&self, key: &CredentialKey) -> Option<Arc<RithmicSession>> {
+        self.sessions.get(key).map(|session| session.clone())
+    }
+
+    /// Log in to the plants for one set of credentials, fails if the credentials already have a session.
+    /// If the order plant is included the account list is requested, so orders can be routed by account.
+    pub async fn connect(&self, key: &CredentialKey, plants: &[SysInfraType]) -> Result<Arc<RithmicSession>, RithmicApiError> {
+        let client = match self.client(key) {
+            Some(client) => client,
+            None => return Err(RithmicApiError::ClientErrorDebug(format!("No client for {} on {}", key.user, key.system)))
+        };
+        let login = self.logins.entry(key.clone()).or_default().clone();
+        // a parallel call for the same credentials waits here and then sees the session stored by the first
+        let _login = login.lock().await;
+        if let Some(session) = self.session(key) {
+            return Err(RithmicApiError::SessionAlreadyActive { user: key.user.clone(), plants: session.plants() });
+        }
+
+        let session = Arc::new(RithmicSession::connect(client.clone(), plants).await?);
+        if session.plant(SysInfraType::OrderPlant).is_some() {
+            let request = RequestAccountList {
+                template_id: TemplateId::RequestAccountList.id(),
+                user_msg: vec![],
+                fcm_id: session.fcm_id().map(str::to_string),
+                ib_id: session.ib_id().map(str::to_string),
+                user_type: client.credentials().user_type,
+            };
+            // the session is only returned once orders can be routed by account
+            match client.request(SysInfraType::OrderPlant, request).await {
+                Ok(responses) => {
+                    for response in &responses {
+                        client.accounts().apply_account_list(response);
+                    }
+                }
+                Err(e) => {
+                    if let Err(e) = session.shutdown_all().await {
+                        eprintln!("Failed to log out of {} on {}: {}", key.user, key.system, e);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        self.sessions.insert(key.clone(), session.clone());
+        Ok(session)
+    }
+
+    /// Log out of every plant of the credentials.
+    pub async fn disconnect(&self, key: &CredentialKey) -> Result<(), RithmicApiError> {
+        match self.sessions.remove(key) {
+            Some((_, session)) => session.shutdown_all().await,
+            None => Ok(()),
+        }
+    }
+
+    /// Log out of every session, the first error is returned after all sessions have been logged out.
+    pub async fn disconnect_all(&self) -> Result<(), RithmicApiError> {
+        let mut result = Ok(());
+        for key in self.sessions.iter().map(|session| session.key().clone()).collect::<Vec<_>>() {
+            if let Err(e) = self.disconnect(&key).await {
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// The credentials whose client has the account in its account cache.
+    pub fn route(&self, account_id: &str) -> Option<CredentialKey> {
+        self.clients
+            .iter()
+            .find(|client| client.value().accounts().get(account_id).is_some())
+            .map(|client| client.key().clone())
+    }
+
+    /// Send an order request on the order plant of the client that owns the account.
+    pub async fn request_for_account<R: RithmicRequest>(&self, account_id: &str, request: R) -> Result<Vec<R::Response>, RithmicApiError> {
+        let key = match self.route(account_id) {
+            Some(key) => key,
+            None => return Err(RithmicApiError::ClientErrorDebug(format!("No client has access to account: {}", account_id)))
+        };
+        let client = match self.client(&key) {
+            Some(client) => client,
+            None => return Err(RithmicApiError::ClientErrorDebug(format!("Client for {} on {} was removed", key.user, key.system)))
+        };
+        client.request(SysInfraType::OrderPlant, request).await
+    }
+
+    /// Account, order and PnL updates from every session connected so far, tagged with the credentials they came from.
+    pub fn account_events(&self) -> impl Stream<Item = TaggedMessage> {
+        let mut streams: Vec<BoxStream<'static, TaggedMessage>> = Vec::new();
+        for session in self.sessions.iter() {
+            for plant in [SysInfraType::OrderPlant, SysInfraType::PnlPlant] {
+                let connection = match session.plant(plant) {
+                    Some(connection) => connection,
+                    None => continue,
+                };
+                let key = session.key().clone();
+                streams.push(connection
+                    .messages()
+                    .filter(|message| futures_util::future::ready(is_account_message(message)))
+                    .map(move |message| TaggedMessage { key: key.clone(), plant, message })
+                    .boxed());
+            }
+        }
+        futures_util::stream::select_all(streams)
+    }
+}
+
+fn is_account_message(message: &RithmicMessage) -> bool {
+    matches!(
+        message,
+        RithmicMessage::AccountPnLPositionUpdate(_)
+            | RithmicMessage::InstrumentPnLPositionUpdate(_)
+            | RithmicMessage::ResponsePnLPositionSnapshot(_)
+            | RithmicMessage::AccountListUpdates(_)
+            | RithmicMessage::AccountRmsUpdates(_)
+            | RithmicMessage::UserAccountUpdate(_)
+            | RithmicMessage::RithmicOrderNotification(_)
+            | RithmicMessage::ExchangeOrderNotification(_)
+            | RithmicMessage::BracketUpdates(_)
+    )
+}
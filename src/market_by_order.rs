@@ -0,0 +1,756 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use futures_util::future::BoxFuture;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use crate::connection::{ConnectionState, PlantConnection};
+use crate::errors::RithmicApiError;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::depth_by_order::{TransactionType, UpdateType};
+use crate::rithmic_proto_objects::rti::{DepthByOrder, DepthByOrderEndEvent, RequestDepthByOrderSnapshot, ResponseDepthByOrderSnapshot};
+use crate::subscriptions::{MarketDataGuard, MarketDataSubscriptions};
+use crate::templates::TemplateId;
+
+/// How long to wait before requesting a snapshot again after a failed request.
+const SNAPSHOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Side {
+    Bid,
+    Ask,
+}
+
+impl Side {
+    /// Buy orders rest on the bid, sell orders on the ask.
+    pub fn from_transaction_type(transaction_type: i32) -> Option<Side> {
+        match TransactionType::try_from(transaction_type) {
+            Ok(TransactionType::Buy) => Some(Side::Bid),
+            Ok(TransactionType::Sell) => Some(Side::Ask),
+            Err(_) => None,
+        }
+    }
+}
+
+/// A price that can be used as a map key, ordered with `f64::total_cmp`.
+#[derive(Clone, Copy, Debug)]
+pub struct Price(pub f64);
+
+impl PartialEq for Price {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Price {}
+
+impl PartialOrd for Price {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Price {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// A resting order, identified by its `depth_order_priority`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookOrder {
+    pub priority: u64,
+    pub side: Side,
+    pub price: f64,
+    pub size: i32,
+    pub exchange_order_id: Option<String>,
+}
+
+/// The orders at one price, aggregated.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookLevel {
+    pub price: f64,
+    pub size: i64,
+    pub order_count: usize,
+}
+
+/// A `sequence_number` was skipped, the book can not be trusted until a new snapshot is applied.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SequenceGap {
+    pub expected: u64,
+    pub received: u64,
+}
+
+/// Updates received while waiting for a snapshot, replayed once it arrives.
+#[allow(clippy::large_enum_variant)] // short lived, only held until the snapshot arrives
+enum Buffered {
+    Update(DepthByOrder),
+    End(DepthByOrderEndEvent),
+}
+
+/// A market by order book built from `ResponseDepthByOrderSnapshot` (116), `DepthByOrder` (160) and `DepthByOrderEndEvent` (161).
+/// Updates are collected and applied together when the end event of their batch arrives, so the book is never read half updated.
+/// Until a snapshot has been applied, and again after a sequence gap, updates are buffered and replayed on top of the next snapshot.
+pub struct MarketByOrderBook {
+    symbol: String,
+    exchange: String,
+    orders: HashMap<u64, BookOrder>,
+    bids: BTreeMap<Price, BTreeSet<u64>>,
+    asks: BTreeMap<Price, BTreeSet<u64>>,
+    /// The sequence number of the last applied batch or snapshot.
+    sequence: Option<u64>,
+    /// The sequence number of the last update accepted into the current batch.
+    last_received: Option<u64>,
+    batch: Vec<DepthByOrder>,
+    synced: bool,
+    buffer: Vec<Buffered>,
+}
+
+impl MarketByOrderBook {
+    pub fn new(symbol: String, exchange: String) -> Self {
+        Self {
+            symbol,
+            exchange,
+            orders: HashMap::new(),
+            bids: BTreeMap::new(),
+            asks: BTreeMap::new(),
+            sequence: None,
+            last_received: None,
+            batch: Vec::new(),
+            synced: false,
+            buffer: Vec::new(),
+        }
+    }
+
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+
+    /// Returns true once a snapshot has been applied and no gap has been found since.
+    pub fn is_synced(&self) -> bool {
+        self.synced
+    }
+
+    /// The sequence number of the last applied batch or snapshot.
+    pub fn sequence(&self) -> Option<u64> {
+        self.sequence
+    }
+
+    /// Returns true if the message is for this book's symbol and exchange.
+    pub fn matches(&self, symbol: Option<&str>, exchange: Option<&str>) -> bool {
+        symbol == Some(self.symbol.as_str()) && (exchange.is_none() || exchange == Some(self.exchange.as_str()))
+    }
+
+    /// Returns true if the end event closes a batch of this book, the symbols and exchanges of the event are parallel lists.
+    pub fn matches_end_event(&self, end: &DepthByOrderEndEvent) -> bool {
+        end.symbol
+            .iter()
+            .enumerate()
+            .any(|(i, symbol)| self.matches(Some(symbol), end.exchange.get(i).map(String::as_str)))
+    }
+
+    /// Drop the book and buffer everything until the next snapshot, eg: after a reconnect or when updates were missed.
+    pub fn invalidate(&mut self) {
+        self.synced = false;
+        self.batch.clear();
+        self.buffer.clear();
+    }
+
+    /// Replace the book with the rows of a snapshot, then replay the updates received while waiting for it.
+    /// Returns an error if the replayed updates have a gap, a new snapshot is needed.
+    pub fn apply_snapshot(&mut self, rows: &[ResponseDepthByOrderSnapshot]) -> Result<(), SequenceGap> {
+        self.orders.clear();
+        self.bids.clear();
+        self.asks.clear();
+        self.batch.clear();
+
+        for row in rows {
+            let side = match row.depth_side.and_then(Side::from_transaction_type) {
+                Some(side) => side,
+                None => continue,
+            };
+            let price = match row.depth_price {
+                Some(price) => price,
+                None => continue,
+            };
+            for (i, size) in row.depth_size.iter().enumerate() {
+                let priority = match row.depth_order_priority.get(i) {
+                    Some(priority) => *priority,
+                    None => continue,
+                };
+                self.insert(BookOrder {
+                    priority,
+                    side,
+                    price,
+                    size: *size,
+                    exchange_order_id: row.exchange_order_id.get(i).cloned(),
+                });
+            }
+        }
+
+        let snapshot_sequence = rows.iter().filter_map(|row| row.sequence_number).max();
+        self.sequence = snapshot_sequence;
+        self.last_received = snapshot_sequence;
+        self.synced = true;
+
+        let mut buffered_events = std::mem::take(&mut self.buffer).into_iter();
+        while let Some(buffered) = buffered_events.next() {
+            match buffered {
+                Buffered::Update(update) => {
+                    // already part of the snapshot
+                    if let (Some(sequence), Some(snapshot_sequence)) = (update.sequence_number, snapshot_sequence) {
+                        if sequence <= snapshot_sequence {
+                            continue;
+                        }
+                    }
+                    if let Err(gap) = self.on_depth_by_order(update) {
+                        // keep the rest for the next snapshot
+                        self.buffer.extend(buffered_events);
+                        return Err(gap);
+                    }
+                }
+                Buffered::End(end) => {
+                    // closes a batch that is already part of the snapshot
+                    if let (Some(sequence), Some(snapshot_sequence)) = (end.sequence_number, snapshot_sequence) {
+                        if sequence <= snapshot_sequence {
+                            continue;
+                        }
+                    }
+                    self.on_end_event(&end);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add an update to the current batch, it is applied when the end event arrives.
+    /// Returns an error if a sequence number was skipped, the book is invalidated and needs a new snapshot.
+    pub fn on_depth_by_order(&mut self, update: DepthByOrder) -> Result<(), SequenceGap> {
+        if !self.synced {
+            self.buffer.push(Buffered::Update(update));
+            return Ok(());
+        }
+        if let (Some(received), Some(last)) = (update.sequence_number, self.last_received) {
+            // updates of one batch may share a sequence number
+            if received < last {
+                return Ok(());
+            }
+            if received > last + 1 {
+                self.invalidate();
+                self.buffer.push(Buffered::Update(update));
+                return Err(SequenceGap { expected: last + 1, received });
+            }
+        }
+        if update.sequence_number.is_some() {
+            self.last_received = update.sequence_number;
+        }
+        self.batch.push(update);
+        Ok(())
+    }
+
+    /// Apply the current batch, returns true if the book changed.
+    pub fn on_end_event(&mut self, end: &DepthByOrderEndEvent) -> bool {
+        if !self.synced {
+            self.buffer.push(Buffered::End(end.clone()));
+            return false;
+        }
+        let batch = std::mem::take(&mut self.batch);
+        let changed = !batch.is_empty();
+        for update in &batch {
+            self.apply(update);
+        }
+        self.sequence = end.sequence_number.or(self.last_received).or(self.sequence);
+        changed
+    }
+
+    fn apply(&mut self, update: &DepthByOrder) {
+        for (i, update_type) in update.update_type.iter().enumerate() {
+            let priority = match update.depth_order_priority.get(i) {
+                Some(priority) => *priority,
+                None => continue,
+            };
+            let prev_price = match update.prev_depth_price_flag.get(i) {
+                Some(true) => update.prev_depth_price.get(i).copied(),
+                _ => None,
+            };
+            match UpdateType::try_from(*update_type) {
+                Ok(UpdateType::Delete) => {
+                    self.remove(priority, prev_price);
+                }
+                Ok(UpdateType::New) | Ok(UpdateType::Change) => {
+                    let existing = self.remove(priority, prev_price);
+                    let side = update.transaction_type.get(i)
+                        .and_then(|transaction_type| Side::from_transaction_type(*transaction_type))
+                        .or(existing.as_ref().map(|order| order.side));
+                    let price = update.depth_price.get(i).copied().or(existing.as_ref().map(|order| order.price));
+                    let size = update.depth_size.get(i).copied().or(existing.as_ref().map(|order| order.size));
+                    let (side, price, size) = match (side, price, size) {
+                        (Some(side), Some(price), Some(size)) => (side, price, size),
+                        _ => continue,
+                    };
+                    let exchange_order_id = update.exchange_order_id.get(i).cloned()
+                        .or(existing.and_then(|order| order.exchange_order_id));
+                    self.insert(BookOrder { priority, side, price, size, exchange_order_id });
+                }
+                Err(_) => {}
+            }
+        }
+    }
+
+    fn insert(&mut self, order: BookOrder) {
+        let levels = match order.side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        levels.entry(Price(order.price)).or_default().insert(order.priority);
+        self.orders.insert(order.priority, order);
+    }
+
+    /// Remove an order, `prev_price` is where the order was resting before it moved.
+    fn remove(&mut self, priority: u64, prev_price: Option<f64>) -> Option<BookOrder> {
+        let order = self.orders.remove(&priority)?;
+        let levels = match order.side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        };
+        for price in [prev_price, Some(order.price)].into_iter().flatten() {
+            if let Some(level) = levels.get_mut(&Price(price)) {
+                if level.remove(&priority) {
+                    if level.is_empty() {
+                        levels.remove(&Price(price));
+                    }
+                    break;
+                }
+            }
+        }
+        Some(order)
+    }
+
+    pub fn order(&self, priority: u64) -> Option<&BookOrder> {
+        self.orders.get(&priority)
+    }
+
+    /// The orders at a price in queue priority order.
+    pub fn orders_at(&self, side: Side, price: f64) -> Vec<&BookOrder> {
+        let levels = match side {
+            Side::Bid => &self.bids,
+            Side::Ask => &self.asks,
+        };
+        match levels.get(&Price(price)) {
+            Some(level) => level.iter().filter_map(|priority| self.orders.get(priority)).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<BookLevel> {
+        self.bids(1).into_iter().next()
+    }
+
+    pub fn best_ask(&self) -> Option<BookLevel> {
+        self.asks(1).into_iter().next()
+    }
+
+    /// The best `depth` bid levels, highest price first.
+    pub fn bids(&self, depth: usize) -> Vec<BookLevel> {
+        self.bids.iter().rev().take(depth).map(|(price, level)| self.level(*price, level)).collect()
+    }
+
+    /// The best `depth` ask levels, lowest price first.
+    pub fn asks(&self, depth: usize) -> Vec<BookLevel> {
+        self.asks.iter().take(depth).map(|(price, level)| self.level(*price, level)).collect()
+    }
+
+    fn level(&self, price: Price, level: &BTreeSet<u64>) -> BookLevel {
+        BookLevel {
+            price: price.0,
+            size: level.iter().filter_map(|priority| self.orders.get(priority)).map(|order| order.size as i64).sum(),
+            order_count: level.len(),
+        }
+    }
+}
+
+/// The state of a tracked book, published after every snapshot and applied batch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookStatus {
+    pub synced: bool,
+    pub sequence: Option<u64>,
+    /// The last gap found, a new snapshot was requested when it was found.
+    pub last_gap: Option<SequenceGap>,
+}
+
+/// A market by order book kept current by a background task, see [`track_market_by_order`].
+/// Dropping the handle stops the task and releases the depth by order subscription.
+pub struct MarketByOrderHandle {
+    book: Arc<RwLock<MarketByOrderBook>>,
+    status: watch::Receiver<BookStatus>,
+    /// Taken by `stop()`, otherwise released when the handle is dropped.
+    guard: Option<MarketDataGuard>,
+    task: JoinHandle<()>,
+}
+
+impl MarketByOrderHandle {
+    /// Read the book, the lock is held while `read` runs so keep it short.
+    pub fn with_book<R>(&self, read: impl FnOnce(&MarketByOrderBook) -> R) -> R {
+        read(&self.book.read().unwrap())
+    }
+
+    pub fn status(&self) -> BookStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Changes after every snapshot and applied batch.
+    pub fn watch_status(&self) -> watch::Receiver<BookStatus> {
+        self.status.clone()
+    }
+
+    /// Stop the task and wait for the depth by order subscription to be released, it is only unsubscribed if no other book holds it.
+    pub async fn stop(mut self) -> Result<(), RithmicApiError> {
+        self.task.abort();
+        match self.guard.take() {
+            Some(guard) => guard.release().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for MarketByOrderHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Hold the depth by order updates of a symbol on the ticker plant and keep a book current.
+/// A snapshot is requested at the start, after every sequence gap and after every reconnect.
+pub async fn track_market_by_order(subscriptions: &MarketDataSubscriptions, symbol: String, exchange: String, request_timeout: Duration) -> Result<MarketByOrderHandle, RithmicApiError> {
+    let connection = match subscriptions.connection().await {
+        Some(connection) => connection,
+        None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+    };
+    // subscribe to the inbound messages first so no update between the subscribe and the snapshot is missed
+    let inbound = connection.subscribe();
+    let guard = subscriptions.subscribe_depth_by_order(&symbol, &exchange).await?;
+    let book = Arc::new(RwLock::new(MarketByOrderBook::new(symbol, exchange)));
+    let (status_sender, status) = watch::channel(BookStatus { synced: false, sequence: None, last_gap: None });
+    let task = tokio::task::spawn(run_market_by_order(connection, inbound, book.clone(), status_sender, request_timeout));
+    Ok(MarketByOrderHandle { book, status, guard: Some(guard), task })
+}
+
+type SnapshotFuture = BoxFuture<'static, Result<Vec<ResponseDepthByOrderSnapshot>, RithmicApiError>>;
+
+async fn run_market_by_order(
+    connection: PlantConnection,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    book: Arc<RwLock<MarketByOrderBook>>,
+    status: watch::Sender<BookStatus>,
+    request_timeout: Duration,
+) {
+    let (symbol, exchange) = {
+        let book = book.read().unwrap();
+        (book.symbol().to_string(), book.exchange().to_string())
+    };
+    let mut state = connection.watch_state();
+    let request_snapshot = |delay: Duration| -> SnapshotFuture {
+        let connection = connection.clone();
+        let request = RequestDepthByOrderSnapshot {
+            template_id: TemplateId::RequestDepthByOrderSnapshot.id(),
+            user_msg: vec![],
+            symbol: Some(symbol.clone()),
+            exchange: Some(exchange.clone()),
+            depth_price: None,
+        };
+        Box::pin(async move {
+            tokio::time::sleep(delay).await;
+            connection.request(request, request_timeout).await
+        })
+    };
+    let publish = |last_gap: Option<SequenceGap>| {
+        let book = book.read().unwrap();
+        status.send_modify(|status| {
+            status.synced = book.is_synced();
+            status.sequence = book.sequence();
+            if last_gap.is_some() {
+                status.last_gap = last_gap;
+            }
+        });
+    };
+
+    let mut snapshot: Option<SnapshotFuture> = Some(request_snapshot(Duration::ZERO));
+
+    loop {
+        tokio::select! {
+            result = poll_optional(&mut snapshot) => {
+                snapshot = None;
+                match result {
+                    Ok(rows) => {
+                        let result = book.write().unwrap().apply_snapshot(&rows);
+                        if let Err(gap) = result {
+                            snapshot = Some(request_snapshot(Duration::ZERO));
+                            publish(Some(gap));
+                        } else {
+                            publish(None);
+                        }
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to request depth by order snapshot for {} {}: {}", symbol, exchange, e);
+                        snapshot = Some(request_snapshot(SNAPSHOT_RETRY_DELAY));
+                    }
+                }
+            }
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::DepthByOrder(update)) => {
+                        if !book.read().unwrap().matches(update.symbol.as_deref(), update.exchange.as_deref()) {
+                            continue;
+                        }
+                        let result = book.write().unwrap().on_depth_by_order(update);
+                        if let Err(gap) = result {
+                            eprintln!("Depth by order gap for {} {}, expected {} received {}", symbol, exchange, gap.expected, gap.received);
+                            if snapshot.is_none() {
+                                snapshot = Some(request_snapshot(Duration::ZERO));
+                            }
+                            publish(Some(gap));
+                        }
+                    }
+                    Ok(RithmicMessage::DepthByOrderEndEvent(end)) => {
+                        if !book.read().unwrap().matches_end_event(&end) {
+                            continue;
+                        }
+                        let changed = book.write().unwrap().on_end_event(&end);
+                        if changed {
+                            publish(None);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Depth by order for {} {} lagged, skipped {} messages, requesting a new snapshot", symbol, exchange, skipped);
+                        book.write().unwrap().invalidate();
+                        if snapshot.is_none() {
+                            snapshot = Some(request_snapshot(Duration::ZERO));
+                        }
+                        publish(None);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let current = state.borrow_and_update().clone();
+                match current {
+                    // the subscription is sent again by the connection, the book needs a new snapshot
+                    ConnectionState::Connected => {
+                        snapshot = Some(request_snapshot(Duration::ZERO));
+                    }
+                    ConnectionState::Reconnecting { .. } => {
+                        book.write().unwrap().invalidate();
+                        snapshot = None;
+                        publish(None);
+                    }
+                    _ if current.is_terminal() => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Await the future if there is one, otherwise never complete.
+async fn poll_optional<F: Future + Unpin>(future: &mut Option<F>) -> F::Output {
+    match future {
+        Some(future) => future.await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUY: i32 = TransactionType::Buy as i32;
+    const SELL: i32 = TransactionType::Sell as i32;
+
+    fn book() -> MarketByOrderBook {
+        MarketByOrderBook::new("ESZ4".to_string(), "CME".to_string())
+    }
+
+    fn snapshot_row(sequence: u64, side: i32, price: f64, orders: &[(u64, i32)]) -> ResponseDepthByOrderSnapshot {
+        ResponseDepthByOrderSnapshot {
+            template_id: TemplateId::ResponseDepthByOrderSnapshot.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            sequence_number: Some(sequence),
+            depth_side: Some(side),
+            depth_price: Some(price),
+            depth_size: orders.iter().map(|(_, size)| *size).collect(),
+            depth_order_priority: orders.iter().map(|(priority, _)| *priority).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn update(sequence: u64, update_type: UpdateType, side: i32, price: f64, size: i32, priority: u64) -> DepthByOrder {
+        DepthByOrder {
+            template_id: TemplateId::DepthByOrder.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            sequence_number: Some(sequence),
+            update_type: vec![update_type as i32],
+            transaction_type: vec![side],
+            depth_price: vec![price],
+            depth_size: vec![size],
+            depth_order_priority: vec![priority],
+            ..Default::default()
+        }
+    }
+
+    fn end(sequence: u64) -> DepthByOrderEndEvent {
+        DepthByOrderEndEvent {
+            template_id: TemplateId::DepthByOrderEndEvent.id(),
+            symbol: vec!["ESZ4".to_string()],
+            exchange: vec!["CME".to_string()],
+            sequence_number: Some(sequence),
+            ..Default::default()
+        }
+    }
+
+    fn synced_book() -> MarketByOrderBook {
+        let mut book = book();
+        book.apply_snapshot(&[
+            snapshot_row(10, BUY, 100.0, &[(1, 5), (2, 3)]),
+            snapshot_row(10, SELL, 100.25, &[(3, 4)]),
+        ]).unwrap();
+        book
+    }
+
+    #[test]
+    fn snapshot_builds_the_levels() {
+        let book = synced_book();
+        assert!(book.is_synced());
+        assert_eq!(book.sequence(), Some(10));
+        assert_eq!(book.best_bid(), Some(BookLevel { price: 100.0, size: 8, order_count: 2 }));
+        assert_eq!(book.best_ask(), Some(BookLevel { price: 100.25, size: 4, order_count: 1 }));
+        let priorities: Vec<u64> = book.orders_at(Side::Bid, 100.0).iter().map(|order| order.priority).collect();
+        assert_eq!(priorities, vec![1, 2]);
+    }
+
+    #[test]
+    fn updates_are_applied_on_the_end_event() {
+        let mut book = synced_book();
+        book.on_depth_by_order(update(11, UpdateType::New, BUY, 99.75, 2, 4)).unwrap();
+        book.on_depth_by_order(update(11, UpdateType::Change, BUY, 100.0, 1, 1)).unwrap();
+        book.on_depth_by_order(update(11, UpdateType::Delete, SELL, 100.25, 0, 3)).unwrap();
+        // nothing changes until the batch is closed
+        assert_eq!(book.bids(5).len(), 1);
+        assert!(book.on_end_event(&end(11)));
+
+        assert_eq!(book.sequence(), Some(11));
+        assert_eq!(book.bids(5), vec![
+            BookLevel { price: 100.0, size: 4, order_count: 2 },
+            BookLevel { price: 99.75, size: 2, order_count: 1 },
+        ]);
+        assert_eq!(book.best_ask(), None);
+        assert!(book.order(3).is_none());
+    }
+
+    #[test]
+    fn change_moves_an_order_to_a_new_price() {
+        let mut book = synced_book();
+        let mut moved = update(11, UpdateType::Change, BUY, 99.5, 5, 1);
+        moved.prev_depth_price = vec![100.0];
+        moved.prev_depth_price_flag = vec![true];
+        book.on_depth_by_order(moved).unwrap();
+        book.on_end_event(&end(11));
+
+        assert_eq!(book.order(1).map(|order| order.price), Some(99.5));
+        assert_eq!(book.bids(5), vec![
+            BookLevel { price: 100.0, size: 3, order_count: 1 },
+            BookLevel { price: 99.5, size: 5, order_count: 1 },
+        ]);
+    }
+
+    #[test]
+    fn gap_invalidates_the_book_until_the_next_snapshot() {
+        let mut book = synced_book();
+        let gap = book.on_depth_by_order(update(13, UpdateType::New, BUY, 99.75, 2, 4)).unwrap_err();
+        assert_eq!(gap, SequenceGap { expected: 11, received: 13 });
+        assert!(!book.is_synced());
+        book.on_end_event(&end(13));
+
+        // the snapshot already covers 11 and 12, the buffered update and end event are replayed on top
+        book.apply_snapshot(&[snapshot_row(12, BUY, 100.0, &[(1, 5)])]).unwrap();
+        assert!(book.is_synced());
+        assert_eq!(book.sequence(), Some(13));
+        assert_eq!(book.bids(5), vec![
+            BookLevel { price: 100.0, size: 5, order_count: 1 },
+            BookLevel { price: 99.75, size: 2, order_count: 1 },
+        ]);
+    }
+
+    #[test]
+    fn updates_before_the_snapshot_are_buffered() {
+        let mut book = book();
+        book.on_depth_by_order(update(10, UpdateType::New, BUY, 99.0, 1, 9)).unwrap();
+        book.on_depth_by_order(update(11, UpdateType::New, BUY, 99.5, 1, 5)).unwrap();
+        assert!(!book.on_end_event(&end(11)));
+        book.apply_snapshot(&[snapshot_row(10, BUY, 100.0, &[(1, 5)])]).unwrap();
+
+        // update 10 is part of the snapshot and is dropped
+        assert!(book.order(9).is_none());
+        assert!(book.order(5).is_some());
+        assert_eq!(book.sequence(), Some(11));
+    }
+
+    #[test]
+    fn buffered_end_events_covered_by_the_snapshot_are_skipped() {
+        let mut book = book();
+        book.on_depth_by_order(update(9, UpdateType::New, BUY, 99.0, 1, 9)).unwrap();
+        book.on_end_event(&end(9));
+        book.apply_snapshot(&[snapshot_row(10, BUY, 100.0, &[(1, 5)])]).unwrap();
+        assert_eq!(book.sequence(), Some(10));
+
+        // the next update follows the snapshot, not the old end event
+        book.on_depth_by_order(update(11, UpdateType::New, BUY, 99.5, 1, 5)).unwrap();
+        book.on_end_event(&end(11));
+        assert!(book.is_synced());
+        assert_eq!(book.sequence(), Some(11));
+        assert!(book.order(9).is_none());
+    }
+
+    #[test]
+    fn updates_of_one_batch_may_share_a_sequence_number() {
+        let mut book = synced_book();
+        book.on_depth_by_order(update(11, UpdateType::New, BUY, 99.75, 2, 4)).unwrap();
+        book.on_depth_by_order(update(11, UpdateType::New, BUY, 99.5, 2, 5)).unwrap();
+        // an old update is ignored
+        book.on_depth_by_order(update(9, UpdateType::Delete, BUY, 100.0, 0, 1)).unwrap();
+        book.on_end_event(&end(11));
+        assert_eq!(book.bids(5).len(), 3);
+        assert!(book.order(1).is_some());
+    }
+
+    #[test]
+    fn matches_end_event_checks_the_exchange_of_the_same_symbol() {
+        let book = book();
+        let mut other_exchange = end(1);
+        other_exchange.exchange = vec!["CBOT".to_string()];
+        assert!(!book.matches_end_event(&other_exchange));
+
+        let mut several = end(1);
+        several.symbol = vec!["ESZ4".to_string(), "NQZ4".to_string()];
+        several.exchange = vec!["CBOT".to_string(), "CME".to_string()];
+        assert!(!book.matches_end_event(&several));
+
+        several.symbol = vec!["NQZ4".to_string(), "ESZ4".to_string()];
+        assert!(book.matches_end_event(&several));
+
+        let mut no_exchange = end(1);
+        no_exchange.exchange.clear();
+        assert!(book.matches_end_event(&no_exchange));
+    }
+}
@@ -0,0 +1,552 @@
+use prost::{Message as ProstMessage};
+use crate::api_client::extract_template_id;
+use crate::errors::RithmicApiError;
+use crate::rithmic_proto_objects::rti::{
+    AccountListUpdates, AccountPnLPositionUpdate, AccountRmsUpdates, BestBidOffer, BracketUpdates, DepthByOrder,
+    DepthByOrderEndEvent, EndOfDayPrices, ExchangeOrderNotification, ForcedLogout, FrontMonthContractUpdate,
+    IndicatorPrices, InstrumentPnLPositionUpdate, LastTrade, MarketMode, OpenInterest, OrderBook, OrderPriceLimits,
+    QuoteStatistics, Reject, RequestAcceptAgreement, RequestAccountList, RequestAccountRmsInfo,
+    RequestAccountRmsUpdates, RequestAuxilliaryReferenceData, RequestBracketOrder, RequestCancelAllOrders,
+    RequestCancelOrder, RequestDepthByOrderSnapshot, RequestDepthByOrderUpdates, RequestEasyToBorrowList,
+    RequestExitPosition, RequestFrontMonthContract, RequestGetInstrumentByUnderlying, RequestGetVolumeAtPrice,
+    RequestGiveTickSizeTypeTable, RequestHeartbeat, RequestLinkOrders, RequestListAcceptedAgreements,
+    RequestListExchangePermissions, RequestListUnacceptedAgreements, RequestLogin, RequestLoginInfo, RequestLogout,
+    RequestMarketDataUpdate, RequestMarketDataUpdateByUnderlying, RequestModifyOrder, RequestModifyOrderReferenceData,
+    RequestNewOrder, RequestOcoOrder, RequestOrderSessionConfig, RequestPnLPositionSnapshot, RequestPnLPositionUpdates,
+    RequestProductCodes, RequestProductRmsInfo, RequestReferenceData, RequestReplayExecutions, RequestResumeBars,
+    RequestRithmicSystemGatewayInfo, RequestRithmicSystemInfo, RequestSearchSymbols,
+    RequestSetRithmicMrktDataSelfCertStatus, RequestShowAgreement, RequestShowBracketStops, RequestShowBrackets,
+    RequestShowOrderHistory, RequestShowOrderHistoryDates, RequestShowOrderHistoryDetail,
+    RequestShowOrderHistorySummary, RequestShowOrders, RequestSubscribeForOrderUpdates,
+    RequestSubscribeToBracketUpdates, RequestTickBarReplay, RequestTickBarUpdate, RequestTimeBarReplay,
+    RequestTimeBarUpdate, RequestTradeRoutes, RequestUpdateStopBracketLevel, RequestUpdateTargetBracketLevel,
+    RequestVolumeProfileMinuteBars, ResponseAcceptAgreement, ResponseAccountList, ResponseAccountRmsInfo,
+    ResponseAccountRmsUpdates, ResponseAuxilliaryReferenceData, ResponseBracketOrder, ResponseCancelAllOrders,
+    ResponseCancelOrder, ResponseDepthByOrderSnapshot, ResponseDepthByOrderUpdates, ResponseEasyToBorrowList,
+    ResponseExitPosition, ResponseFrontMonthContract, ResponseGetInstrumentByUnderlying,
+    ResponseGetInstrumentByUnderlyingKeys, ResponseGetVolumeAtPrice, ResponseGiveTickSizeTypeTable, ResponseHeartbeat,
+    ResponseLinkOrders, ResponseListAcceptedAgreements, ResponseListExchangePermissions,
+    ResponseListUnacceptedAgreements, ResponseLogin, ResponseLoginInfo, ResponseLogout, ResponseMarketDataUpdate,
+    ResponseMarketDataUpdateByUnderlying, ResponseModifyOrder, ResponseModifyOrderReferenceData, ResponseNewOrder,
+    ResponseOcoOrder, ResponseOrderSessionConfig, ResponsePnLPositionSnapshot, ResponsePnLPositionUpdates,
+    ResponseProductCodes, ResponseProductRmsInfo, ResponseReferenceData, ResponseReplayExecutions, ResponseResumeBars,
+    ResponseRithmicSystemGatewayInfo, ResponseRithmicSystemInfo, ResponseSearchSymbols,
+    ResponseSetRithmicMrktDataSelfCertStatus, ResponseShowAgreement, ResponseShowBracketStops, ResponseShowBrackets,
+    ResponseShowOrderHistory, ResponseShowOrderHistoryDates, ResponseShowOrderHistoryDetail,
+    ResponseShowOrderHistorySummary, ResponseShowOrders, ResponseSubscribeForOrderUpdates,
+    ResponseSubscribeToBracketUpdates, ResponseTickBarReplay, ResponseTickBarUpdate, ResponseTimeBarReplay,
+    ResponseTimeBarUpdate, ResponseTradeRoutes, ResponseUpdateStopBracketLevel, ResponseUpdateTargetBracketLevel,
+    ResponseVolumeProfileMinuteBars, RithmicOrderNotification, SymbolMarginRate, TickBar, TimeBar, TradeRoute,
+    TradeStatistics, UpdateEasyToBorrowList, UserAccountUpdate
+};
+
+/// Generates [`RithmicMessage`], [`decode_message`] and [`TemplateId`] from a `template_id => Type` table.
+/// A request lists the types the server answers it with, `template_id => Request => Response | OtherResponse`.
+/// Every generated type in `rti` appears exactly once, except `MessageType` which only carries a template_id and is never sent on its own.
+macro_rules! rithmic_messages {
+    ($($template_id:literal => $name:ident $(=> $($response:ident)|+)?),* $(,)?) => {
+        /// Registry of every rithmic template_id.
+        /// Each variant has the same name as the generated proto type it identifies, so `TemplateId::ResponseLogin as i32 == 11`.
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+        #[repr(i32)]
+        pub enum TemplateId {
+            $($name = $template_id,)*
+        }
+
+        impl TemplateId {
+            /// Every template, in template_id order.
+            pub const ALL: &'static [TemplateId] = &[$(TemplateId::$name,)*];
+
+            /// Look up the template for a raw template_id, returns None if the id is not part of the protocol.
+            pub fn from_i32(template_id: i32) -> Option<TemplateId> {
+                match template_id {
+                    $($template_id => Some(TemplateId::$name),)*
+                    _ => None,
+                }
+            }
+
+            /// The name of the generated proto type for this template, eg: "ResponseLogin".
+            pub fn message_type(&self) -> &'static str {
+                match self {
+                    $(TemplateId::$name => stringify!($name),)*
+                }
+            }
+
+            /// The templates the server uses to answer this request.
+            /// Empty for server-originated templates.
+            pub fn responses(&self) -> &'static [TemplateId] {
+                match self {
+                    $(TemplateId::$name => &[$($(TemplateId::$response),+)?],)*
+                }
+            }
+
+            /// The request template this response answers.
+            /// None for requests and for unsolicited updates such as LastTrade or RithmicOrderNotification.
+            pub fn request(&self) -> Option<TemplateId> {
+                match self {
+                    $($($(TemplateId::$response => Some(TemplateId::$name),)+)?)*
+                    _ => None,
+                }
+            }
+        }
+
+        /// A decoded message from any rithmic plant, one variant per generated proto type.
+        /// Match on this instead of decoding each template_id by hand.
+        // Variants are not boxed so that they can be matched and moved out without an extra allocation per message.
+        #[allow(clippy::large_enum_variant)]
+        #[derive(Clone, Debug, PartialEq)]
+        pub enum RithmicMessage {
+            $($name($name),)*
+        }
+
+        impl RithmicMessage {
+            /// The template_id that identifies this message type on the wire.
+            pub fn template_id(&self) -> i32 {
+                match self {
+                    $(RithmicMessage::$name(_) => $template_id,)*
+                }
+            }
+
+            /// The registry entry for this message, see [`TemplateId`] for plant, direction and request/response pairing.
+            pub fn template(&self) -> TemplateId {
+                match self {
+                    $(RithmicMessage::$name(_) => TemplateId::$name,)*
+                }
+            }
+
+            /// Encode the message without the length header, use [`crate::codec::RithmicCodec`] to frame it.
+            pub fn encode_to_vec(&self) -> Vec<u8> {
+                match self {
+                    $(RithmicMessage::$name(message) => message.encode_to_vec(),)*
+                }
+            }
+
+            /// The name of the proto message type, eg: "ResponseLogin".
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(RithmicMessage::$name(_) => stringify!($name),)*
+                }
+            }
+        }
+
+        $(
+        impl From<$name> for RithmicMessage {
+            fn from(message: $name) -> Self {
+                RithmicMessage::$name(message)
+            }
+        }
+
+        impl TryFrom<RithmicMessage> for $name {
+            type Error = RithmicMessage;
+
+            /// Returns the message back if it is a different type.
+            fn try_from(message: RithmicMessage) -> Result<Self, Self::Error> {
+                match message {
+                    RithmicMessage::$name(message) => Ok(message),
+                    other => Err(other),
+                }
+            }
+        }
+        )*
+
+        /// Decode the protobuf payload of a single frame (without the 4-byte length header) into the concrete type for its template_id.
+        /// Returns an error if the template_id is missing or unknown, or if the payload does not decode as the expected type.
+        pub fn decode_message(bytes: &[u8]) -> Result<RithmicMessage, RithmicApiError> {
+            let template_id = match extract_template_id(bytes) {
+                Some(template_id) => template_id,
+                None => return Err(RithmicApiError::MissingTemplateId)
+            };
+            match template_id {
+                $($template_id => Ok(RithmicMessage::$name($name::decode(bytes)?)),)*
+                _ => Err(RithmicApiError::UnknownTemplateId(template_id))
+            }
+        }
+    };
+}
+
+rithmic_messages! {
+    10 => RequestLogin => ResponseLogin,
+    11 => ResponseLogin,
+    12 => RequestLogout => ResponseLogout,
+    13 => ResponseLogout,
+    14 => RequestReferenceData => ResponseReferenceData,
+    15 => ResponseReferenceData,
+    16 => RequestRithmicSystemInfo => ResponseRithmicSystemInfo,
+    17 => ResponseRithmicSystemInfo,
+    18 => RequestHeartbeat => ResponseHeartbeat,
+    19 => ResponseHeartbeat,
+    20 => RequestRithmicSystemGatewayInfo => ResponseRithmicSystemGatewayInfo,
+    21 => ResponseRithmicSystemGatewayInfo,
+    75 => Reject,
+    76 => UserAccountUpdate,
+    77 => ForcedLogout,
+    100 => RequestMarketDataUpdate => ResponseMarketDataUpdate,
+    101 => ResponseMarketDataUpdate,
+    102 => RequestGetInstrumentByUnderlying => ResponseGetInstrumentByUnderlying | ResponseGetInstrumentByUnderlyingKeys,
+    103 => ResponseGetInstrumentByUnderlying,
+    104 => ResponseGetInstrumentByUnderlyingKeys,
+    105 => RequestMarketDataUpdateByUnderlying => ResponseMarketDataUpdateByUnderlying,
+    106 => ResponseMarketDataUpdateByUnderlying,
+    107 => RequestGiveTickSizeTypeTable => ResponseGiveTickSizeTypeTable,
+    108 => ResponseGiveTickSizeTypeTable,
+    109 => RequestSearchSymbols => ResponseSearchSymbols,
+    110 => ResponseSearchSymbols,
+    111 => RequestProductCodes => ResponseProductCodes,
+    112 => ResponseProductCodes,
+    113 => RequestFrontMonthContract => ResponseFrontMonthContract,
+    114 => ResponseFrontMonthContract,
+    115 => RequestDepthByOrderSnapshot => ResponseDepthByOrderSnapshot,
+    116 => ResponseDepthByOrderSnapshot,
+    117 => RequestDepthByOrderUpdates => ResponseDepthByOrderUpdates,
+    118 => ResponseDepthByOrderUpdates,
+    119 => RequestGetVolumeAtPrice => ResponseGetVolumeAtPrice,
+    120 => ResponseGetVolumeAtPrice,
+    121 => RequestAuxilliaryReferenceData => ResponseAuxilliaryReferenceData,
+    122 => ResponseAuxilliaryReferenceData,
+    150 => LastTrade,
+    151 => BestBidOffer,
+    152 => TradeStatistics,
+    153 => QuoteStatistics,
+    154 => IndicatorPrices,
+    155 => EndOfDayPrices,
+    156 => OrderBook,
+    157 => MarketMode,
+    158 => OpenInterest,
+    159 => FrontMonthContractUpdate,
+    160 => DepthByOrder,
+    161 => DepthByOrderEndEvent,
+    162 => SymbolMarginRate,
+    163 => OrderPriceLimits,
+    200 => RequestTimeBarUpdate => ResponseTimeBarUpdate,
+    201 => ResponseTimeBarUpdate,
+    202 => RequestTimeBarReplay => ResponseTimeBarReplay,
+    203 => ResponseTimeBarReplay,
+    204 => RequestTickBarUpdate => ResponseTickBarUpdate,
+    205 => ResponseTickBarUpdate,
+    206 => RequestTickBarReplay => ResponseTickBarReplay,
+    207 => ResponseTickBarReplay,
+    208 => RequestVolumeProfileMinuteBars => ResponseVolumeProfileMinuteBars,
+    209 => ResponseVolumeProfileMinuteBars,
+    210 => RequestResumeBars => ResponseResumeBars,
+    211 => ResponseResumeBars,
+    250 => TimeBar,
+    251 => TickBar,
+    300 => RequestLoginInfo => ResponseLoginInfo,
+    301 => ResponseLoginInfo,
+    302 => RequestAccountList => ResponseAccountList,
+    303 => ResponseAccountList,
+    304 => RequestAccountRmsInfo => ResponseAccountRmsInfo,
+    305 => ResponseAccountRmsInfo,
+    306 => RequestProductRmsInfo => ResponseProductRmsInfo,
+    307 => ResponseProductRmsInfo,
+    308 => RequestSubscribeForOrderUpdates => ResponseSubscribeForOrderUpdates,
+    309 => ResponseSubscribeForOrderUpdates,
+    310 => RequestTradeRoutes => ResponseTradeRoutes,
+    311 => ResponseTradeRoutes,
+    312 => RequestNewOrder => ResponseNewOrder,
+    313 => ResponseNewOrder,
+    314 => RequestModifyOrder => ResponseModifyOrder,
+    315 => ResponseModifyOrder,
+    316 => RequestCancelOrder => ResponseCancelOrder,
+    317 => ResponseCancelOrder,
+    318 => RequestShowOrderHistoryDates => ResponseShowOrderHistoryDates,
+    319 => ResponseShowOrderHistoryDates,
+    320 => RequestShowOrders => ResponseShowOrders,
+    321 => ResponseShowOrders,
+    322 => RequestShowOrderHistory => ResponseShowOrderHistory,
+    323 => ResponseShowOrderHistory,
+    324 => RequestShowOrderHistorySummary => ResponseShowOrderHistorySummary,
+    325 => ResponseShowOrderHistorySummary,
+    326 => RequestShowOrderHistoryDetail => ResponseShowOrderHistoryDetail,
+    327 => ResponseShowOrderHistoryDetail,
+    328 => RequestOcoOrder => ResponseOcoOrder,
+    329 => ResponseOcoOrder,
+    330 => RequestBracketOrder => ResponseBracketOrder,
+    331 => ResponseBracketOrder,
+    332 => RequestUpdateTargetBracketLevel => ResponseUpdateTargetBracketLevel,
+    333 => ResponseUpdateTargetBracketLevel,
+    334 => RequestUpdateStopBracketLevel => ResponseUpdateStopBracketLevel,
+    335 => ResponseUpdateStopBracketLevel,
+    336 => RequestSubscribeToBracketUpdates => ResponseSubscribeToBracketUpdates,
+    337 => ResponseSubscribeToBracketUpdates,
+    338 => RequestShowBrackets => ResponseShowBrackets,
+    339 => ResponseShowBrackets,
+    340 => RequestShowBracketStops => ResponseShowBracketStops,
+    341 => ResponseShowBracketStops,
+    342 => RequestListExchangePermissions => ResponseListExchangePermissions,
+    343 => ResponseListExchangePermissions,
+    344 => RequestLinkOrders => ResponseLinkOrders,
+    345 => ResponseLinkOrders,
+    346 => RequestCancelAllOrders => ResponseCancelAllOrders,
+    347 => ResponseCancelAllOrders,
+    348 => RequestEasyToBorrowList => ResponseEasyToBorrowList,
+    349 => ResponseEasyToBorrowList,
+    350 => TradeRoute,
+    351 => RithmicOrderNotification,
+    352 => ExchangeOrderNotification,
+    353 => BracketUpdates,
+    354 => AccountListUpdates,
+    355 => UpdateEasyToBorrowList,
+    356 => AccountRmsUpdates,
+    400 => RequestPnLPositionUpdates => ResponsePnLPositionUpdates,
+    401 => ResponsePnLPositionUpdates,
+    402 => RequestPnLPositionSnapshot => ResponsePnLPositionSnapshot,
+    403 => ResponsePnLPositionSnapshot,
+    450 => InstrumentPnLPositionUpdate,
+    451 => AccountPnLPositionUpdate,
+    500 => RequestListUnacceptedAgreements => ResponseListUnacceptedAgreements,
+    501 => ResponseListUnacceptedAgreements,
+    502 => RequestListAcceptedAgreements => ResponseListAcceptedAgreements,
+    503 => ResponseListAcceptedAgreements,
+    504 => RequestAcceptAgreement => ResponseAcceptAgreement,
+    505 => ResponseAcceptAgreement,
+    506 => RequestShowAgreement => ResponseShowAgreement,
+    507 => ResponseShowAgreement,
+    508 => RequestSetRithmicMrktDataSelfCertStatus => ResponseSetRithmicMrktDataSelfCertStatus,
+    509 => ResponseSetRithmicMrktDataSelfCertStatus,
+    3500 => RequestModifyOrderReferenceData => ResponseModifyOrderReferenceData,
+    3501 => ResponseModifyOrderReferenceData,
+    3502 => RequestOrderSessionConfig => ResponseOrderSessionConfig,
+    3503 => ResponseOrderSessionConfig,
+    3504 => RequestExitPosition => ResponseExitPosition,
+    3505 => ResponseExitPosition,
+    3506 => RequestReplayExecutions => ResponseReplayExecutions,
+    3507 => ResponseReplayExecutions,
+    3508 => RequestAccountRmsUpdates => ResponseAccountRmsUpdates,
+    3509 => ResponseAccountRmsUpdates,
+}
+
+impl RithmicMessage {
+    /// The `user_msg` strings echoed back by the server, empty for types without the field.
+    pub fn user_msg(&self) -> &[String] {
+        match self {
+            RithmicMessage::RequestLogin(message) => &message.user_msg,
+            RithmicMessage::ResponseLogin(message) => &message.user_msg,
+            RithmicMessage::RequestLogout(message) => &message.user_msg,
+            RithmicMessage::ResponseLogout(message) => &message.user_msg,
+            RithmicMessage::RequestReferenceData(message) => &message.user_msg,
+            RithmicMessage::ResponseReferenceData(message) => &message.user_msg,
+            RithmicMessage::RequestRithmicSystemInfo(message) => &message.user_msg,
+            RithmicMessage::ResponseRithmicSystemInfo(message) => &message.user_msg,
+            RithmicMessage::RequestHeartbeat(message) => &message.user_msg,
+            RithmicMessage::ResponseHeartbeat(message) => &message.user_msg,
+            RithmicMessage::RequestRithmicSystemGatewayInfo(message) => &message.user_msg,
+            RithmicMessage::ResponseRithmicSystemGatewayInfo(message) => &message.user_msg,
+            RithmicMessage::Reject(message) => &message.user_msg,
+            RithmicMessage::RequestMarketDataUpdate(message) => &message.user_msg,
+            RithmicMessage::ResponseMarketDataUpdate(message) => &message.user_msg,
+            RithmicMessage::RequestGetInstrumentByUnderlying(message) => &message.user_msg,
+            RithmicMessage::ResponseGetInstrumentByUnderlying(message) => &message.user_msg,
+            RithmicMessage::ResponseGetInstrumentByUnderlyingKeys(message) => &message.user_msg,
+            RithmicMessage::RequestMarketDataUpdateByUnderlying(message) => &message.user_msg,
+            RithmicMessage::ResponseMarketDataUpdateByUnderlying(message) => &message.user_msg,
+            RithmicMessage::RequestGiveTickSizeTypeTable(message) => &message.user_msg,
+            RithmicMessage::ResponseGiveTickSizeTypeTable(message) => &message.user_msg,
+            RithmicMessage::RequestSearchSymbols(message) => &message.user_msg,
+            RithmicMessage::ResponseSearchSymbols(message) => &message.user_msg,
+            RithmicMessage::RequestProductCodes(message) => &message.user_msg,
+            RithmicMessage::ResponseProductCodes(message) => &message.user_msg,
+            RithmicMessage::RequestFrontMonthContract(message) => &message.user_msg,
+            RithmicMessage::ResponseFrontMonthContract(message) => &message.user_msg,
+            RithmicMessage::RequestDepthByOrderSnapshot(message) => &message.user_msg,
+            RithmicMessage::ResponseDepthByOrderSnapshot(message) => &message.user_msg,
+            RithmicMessage::RequestDepthByOrderUpdates(message) => &message.user_msg,
+            RithmicMessage::ResponseDepthByOrderUpdates(message) => &message.user_msg,
+            RithmicMessage::RequestGetVolumeAtPrice(message) => &message.user_msg,
+            RithmicMessage::ResponseGetVolumeAtPrice(message) => &message.user_msg,
+            RithmicMessage::RequestAuxilliaryReferenceData(message) => &message.user_msg,
+            RithmicMessage::ResponseAuxilliaryReferenceData(message) => &message.user_msg,
+            RithmicMessage::RequestTimeBarUpdate(message) => &message.user_msg,
+            RithmicMessage::ResponseTimeBarUpdate(message) => &message.user_msg,
+            RithmicMessage::RequestTimeBarReplay(message) => &message.user_msg,
+            RithmicMessage::ResponseTimeBarReplay(message) => &message.user_msg,
+            RithmicMessage::RequestTickBarUpdate(message) => &message.user_msg,
+            RithmicMessage::ResponseTickBarUpdate(message) => &message.user_msg,
+            RithmicMessage::RequestTickBarReplay(message) => &message.user_msg,
+            RithmicMessage::ResponseTickBarReplay(message) => &message.user_msg,
+            RithmicMessage::RequestVolumeProfileMinuteBars(message) => &message.user_msg,
+            RithmicMessage::ResponseVolumeProfileMinuteBars(message) => &message.user_msg,
+            RithmicMessage::RequestResumeBars(message) => &message.user_msg,
+            RithmicMessage::ResponseResumeBars(message) => &message.user_msg,
+            RithmicMessage::RequestLoginInfo(message) => &message.user_msg,
+            RithmicMessage::ResponseLoginInfo(message) => &message.user_msg,
+            RithmicMessage::RequestAccountList(message) => &message.user_msg,
+            RithmicMessage::ResponseAccountList(message) => &message.user_msg,
+            RithmicMessage::RequestAccountRmsInfo(message) => &message.user_msg,
+            RithmicMessage::ResponseAccountRmsInfo(message) => &message.user_msg,
+            RithmicMessage::RequestProductRmsInfo(message) => &message.user_msg,
+            RithmicMessage::ResponseProductRmsInfo(message) => &message.user_msg,
+            RithmicMessage::RequestSubscribeForOrderUpdates(message) => &message.user_msg,
+            RithmicMessage::ResponseSubscribeForOrderUpdates(message) => &message.user_msg,
+            RithmicMessage::RequestTradeRoutes(message) => &message.user_msg,
+            RithmicMessage::ResponseTradeRoutes(message) => &message.user_msg,
+            RithmicMessage::RequestNewOrder(message) => &message.user_msg,
+            RithmicMessage::ResponseNewOrder(message) => &message.user_msg,
+            RithmicMessage::RequestModifyOrder(message) => &message.user_msg,
+            RithmicMessage::ResponseModifyOrder(message) => &message.user_msg,
+            RithmicMessage::RequestCancelOrder(message) => &message.user_msg,
+            RithmicMessage::ResponseCancelOrder(message) => &message.user_msg,
+            RithmicMessage::RequestShowOrderHistoryDates(message) => &message.user_msg,
+            RithmicMessage::ResponseShowOrderHistoryDates(message) => &message.user_msg,
+            RithmicMessage::RequestShowOrders(message) => &message.user_msg,
+            RithmicMessage::ResponseShowOrders(message) => &message.user_msg,
+            RithmicMessage::RequestShowOrderHistory(message) => &message.user_msg,
+            RithmicMessage::ResponseShowOrderHistory(message) => &message.user_msg,
+            RithmicMessage::RequestShowOrderHistorySummary(message) => &message.user_msg,
+            RithmicMessage::ResponseShowOrderHistorySummary(message) => &message.user_msg,
+            RithmicMessage::RequestShowOrderHistoryDetail(message) => &message.user_msg,
+            RithmicMessage::ResponseShowOrderHistoryDetail(message) => &message.user_msg,
+            RithmicMessage::RequestOcoOrder(message) => &message.user_msg,
+            RithmicMessage::ResponseOcoOrder(message) => &message.user_msg,
+            RithmicMessage::RequestBracketOrder(message) => &message.user_msg,
+            RithmicMessage::ResponseBracketOrder(message) => &message.user_msg,
+            RithmicMessage::RequestUpdateTargetBracketLevel(message) => &message.user_msg,
+            RithmicMessage::ResponseUpdateTargetBracketLevel(message) => &message.user_msg,
+            RithmicMessage::RequestUpdateStopBracketLevel(message) => &message.user_msg,
+            RithmicMessage::ResponseUpdateStopBracketLevel(message) => &message.user_msg,
+            RithmicMessage::RequestSubscribeToBracketUpdates(message) => &message.user_msg,
+            RithmicMessage::ResponseSubscribeToBracketUpdates(message) => &message.user_msg,
+            RithmicMessage::RequestShowBrackets(message) => &message.user_msg,
+            RithmicMessage::ResponseShowBrackets(message) => &message.user_msg,
+            RithmicMessage::RequestShowBracketStops(message) => &message.user_msg,
+            RithmicMessage::ResponseShowBracketStops(message) => &message.user_msg,
+            RithmicMessage::RequestListExchangePermissions(message) => &message.user_msg,
+            RithmicMessage::ResponseListExchangePermissions(message) => &message.user_msg,
+            RithmicMessage::RequestLinkOrders(message) => &message.user_msg,
+            RithmicMessage::ResponseLinkOrders(message) => &message.user_msg,
+            RithmicMessage::RequestCancelAllOrders(message) => &message.user_msg,
+            RithmicMessage::ResponseCancelAllOrders(message) => &message.user_msg,
+            RithmicMessage::RequestEasyToBorrowList(message) => &message.user_msg,
+            RithmicMessage::ResponseEasyToBorrowList(message) => &message.user_msg,
+            RithmicMessage::RequestPnLPositionUpdates(message) => &message.user_msg,
+            RithmicMessage::ResponsePnLPositionUpdates(message) => &message.user_msg,
+            RithmicMessage::RequestPnLPositionSnapshot(message) => &message.user_msg,
+            RithmicMessage::ResponsePnLPositionSnapshot(message) => &message.user_msg,
+            RithmicMessage::RequestListUnacceptedAgreements(message) => &message.user_msg,
+            RithmicMessage::ResponseListUnacceptedAgreements(message) => &message.user_msg,
+            RithmicMessage::RequestListAcceptedAgreements(message) => &message.user_msg,
+            RithmicMessage::ResponseListAcceptedAgreements(message) => &message.user_msg,
+            RithmicMessage::RequestAcceptAgreement(message) => &message.user_msg,
+            RithmicMessage::ResponseAcceptAgreement(message) => &message.user_msg,
+            RithmicMessage::RequestShowAgreement(message) => &message.user_msg,
+            RithmicMessage::ResponseShowAgreement(message) => &message.user_msg,
+            RithmicMessage::RequestSetRithmicMrktDataSelfCertStatus(message) => &message.user_msg,
+            RithmicMessage::ResponseSetRithmicMrktDataSelfCertStatus(message) => &message.user_msg,
+            RithmicMessage::RequestModifyOrderReferenceData(message) => &message.user_msg,
+            RithmicMessage::ResponseModifyOrderReferenceData(message) => &message.user_msg,
+            RithmicMessage::RequestOrderSessionConfig(message) => &message.user_msg,
+            RithmicMessage::ResponseOrderSessionConfig(message) => &message.user_msg,
+            RithmicMessage::RequestExitPosition(message) => &message.user_msg,
+            RithmicMessage::ResponseExitPosition(message) => &message.user_msg,
+            RithmicMessage::RequestReplayExecutions(message) => &message.user_msg,
+            RithmicMessage::ResponseReplayExecutions(message) => &message.user_msg,
+            RithmicMessage::RequestAccountRmsUpdates(message) => &message.user_msg,
+            RithmicMessage::ResponseAccountRmsUpdates(message) => &message.user_msg,
+            _ => &[],
+        }
+    }
+
+    /// The `rp_code` of a response, element 0 is the numeric code and the rest is text.
+    /// Only the final message of a response carries an rp_code, it is empty for updates and for the rows of a multi-part response.
+    pub fn rp_code(&self) -> &[String] {
+        match self {
+            RithmicMessage::ResponseLogin(message) => &message.rp_code,
+            RithmicMessage::ResponseLogout(message) => &message.rp_code,
+            RithmicMessage::ResponseReferenceData(message) => &message.rp_code,
+            RithmicMessage::ResponseRithmicSystemInfo(message) => &message.rp_code,
+            RithmicMessage::ResponseHeartbeat(message) => &message.rp_code,
+            RithmicMessage::ResponseRithmicSystemGatewayInfo(message) => &message.rp_code,
+            RithmicMessage::Reject(message) => &message.rp_code,
+            RithmicMessage::ResponseMarketDataUpdate(message) => &message.rp_code,
+            RithmicMessage::ResponseGetInstrumentByUnderlying(message) => &message.rp_code,
+            RithmicMessage::ResponseGetInstrumentByUnderlyingKeys(message) => &message.rp_code,
+            RithmicMessage::ResponseMarketDataUpdateByUnderlying(message) => &message.rp_code,
+            RithmicMessage::ResponseGiveTickSizeTypeTable(message) => &message.rp_code,
+            RithmicMessage::ResponseSearchSymbols(message) => &message.rp_code,
+            RithmicMessage::ResponseProductCodes(message) => &message.rp_code,
+            RithmicMessage::ResponseFrontMonthContract(message) => &message.rp_code,
+            RithmicMessage::ResponseDepthByOrderSnapshot(message) => &message.rp_code,
+            RithmicMessage::ResponseDepthByOrderUpdates(message) => &message.rp_code,
+            RithmicMessage::ResponseGetVolumeAtPrice(message) => &message.rp_code,
+            RithmicMessage::ResponseAuxilliaryReferenceData(message) => &message.rp_code,
+            RithmicMessage::ResponseTimeBarUpdate(message) => &message.rp_code,
+            RithmicMessage::ResponseTimeBarReplay(message) => &message.rp_code,
+            RithmicMessage::ResponseTickBarUpdate(message) => &message.rp_code,
+            RithmicMessage::ResponseTickBarReplay(message) => &message.rp_code,
+            RithmicMessage::ResponseVolumeProfileMinuteBars(message) => &message.rp_code,
+            RithmicMessage::ResponseResumeBars(message) => &message.rp_code,
+            RithmicMessage::ResponseLoginInfo(message) => &message.rp_code,
+            RithmicMessage::ResponseAccountList(message) => &message.rp_code,
+            RithmicMessage::ResponseAccountRmsInfo(message) => &message.rp_code,
+            RithmicMessage::ResponseProductRmsInfo(message) => &message.rp_code,
+            RithmicMessage::ResponseSubscribeForOrderUpdates(message) => &message.rp_code,
+            RithmicMessage::ResponseTradeRoutes(message) => &message.rp_code,
+            RithmicMessage::ResponseNewOrder(message) => &message.rp_code,
+            RithmicMessage::ResponseModifyOrder(message) => &message.rp_code,
+            RithmicMessage::ResponseCancelOrder(message) => &message.rp_code,
+            RithmicMessage::ResponseShowOrderHistoryDates(message) => &message.rp_code,
+            RithmicMessage::ResponseShowOrders(message) => &message.rp_code,
+            RithmicMessage::ResponseShowOrderHistory(message) => &message.rp_code,
+            RithmicMessage::ResponseShowOrderHistorySummary(message) => &message.rp_code,
+            RithmicMessage::ResponseShowOrderHistoryDetail(message) => &message.rp_code,
+            RithmicMessage::ResponseOcoOrder(message) => &message.rp_code,
+            RithmicMessage::ResponseBracketOrder(message) => &message.rp_code,
+            RithmicMessage::ResponseUpdateTargetBracketLevel(message) => &message.rp_code,
+            RithmicMessage::ResponseUpdateStopBracketLevel(message) => &message.rp_code,
+            RithmicMessage::ResponseSubscribeToBracketUpdates(message) => &message.rp_code,
+            RithmicMessage::ResponseShowBrackets(message) => &message.rp_code,
+            RithmicMessage::ResponseShowBracketStops(message) => &message.rp_code,
+            RithmicMessage::ResponseListExchangePermissions(message) => &message.rp_code,
+            RithmicMessage::ResponseLinkOrders(message) => &message.rp_code,
+            RithmicMessage::ResponseCancelAllOrders(message) => &message.rp_code,
+            RithmicMessage::ResponseEasyToBorrowList(message) => &message.rp_code,
+            RithmicMessage::ResponsePnLPositionUpdates(message) => &message.rp_code,
+            RithmicMessage::ResponsePnLPositionSnapshot(message) => &message.rp_code,
+            RithmicMessage::ResponseListUnacceptedAgreements(message) => &message.rp_code,
+            RithmicMessage::ResponseListAcceptedAgreements(message) => &message.rp_code,
+            RithmicMessage::ResponseAcceptAgreement(message) => &message.rp_code,
+            RithmicMessage::ResponseShowAgreement(message) => &message.rp_code,
+            RithmicMessage::ResponseSetRithmicMrktDataSelfCertStatus(message) => &message.rp_code,
+            RithmicMessage::ResponseModifyOrderReferenceData(message) => &message.rp_code,
+            RithmicMessage::ResponseOrderSessionConfig(message) => &message.rp_code,
+            RithmicMessage::ResponseExitPosition(message) => &message.rp_code,
+            RithmicMessage::ResponseReplayExecutions(message) => &message.rp_code,
+            RithmicMessage::ResponseAccountRmsUpdates(message) => &message.rp_code,
+            _ => &[],
+        }
+    }
+
+    /// The `rq_handler_rp_code` that marks each row of a multi-part response, empty for other messages.
+    pub fn rq_handler_rp_code(&self) -> &[String] {
+        match self {
+            RithmicMessage::ResponseGetInstrumentByUnderlying(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseGiveTickSizeTypeTable(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseSearchSymbols(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseProductCodes(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseDepthByOrderSnapshot(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseGetVolumeAtPrice(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseTimeBarReplay(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseTickBarReplay(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseVolumeProfileMinuteBars(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseAccountList(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseAccountRmsInfo(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseProductRmsInfo(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseTradeRoutes(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseNewOrder(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseModifyOrder(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseCancelOrder(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseShowOrderHistoryDates(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseOcoOrder(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseBracketOrder(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseShowBrackets(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseShowBracketStops(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseListExchangePermissions(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseEasyToBorrowList(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseListUnacceptedAgreements(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseListAcceptedAgreements(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseShowAgreement(message) => &message.rq_handler_rp_code,
+            RithmicMessage::ResponseExitPosition(message) => &message.rq_handler_rp_code,
+            _ => &[],
+        }
+    }
+}
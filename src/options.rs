@@ -0,0 +1,154 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+use crate::connection::PlantConnection;
+use crate::errors::RithmicApiError;
+use crate::instruments::Instrument;
+use crate::market_by_order::Price;
+use crate::rithmic_proto_objects::rti::RequestGetInstrumentByUnderlying;
+use crate::templates::TemplateId;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum OptionRight {
+    Call,
+    Put,
+}
+
+impl OptionRight {
+    /// Parse the `put_call_indicator` of an instrument, eg: "C", "CALL", "P" or "PUT".
+    pub fn from_indicator(indicator: &str) -> Option<OptionRight> {
+        match indicator.trim().chars().next().map(|c| c.to_ascii_uppercase()) {
+            Some('C') => Some(OptionRight::Call),
+            Some('P') => Some(OptionRight::Put),
+            _ => None,
+        }
+    }
+}
+
+/// The call and put at one strike.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptionStrike {
+    pub strike: f64,
+    pub call: Option<Instrument>,
+    pub put: Option<Instrument>,
+}
+
+impl OptionStrike {
+    pub fn contract(&self, right: OptionRight) -> Option<&Instrument> {
+        match right {
+            OptionRight::Call => self.call.as_ref(),
+            OptionRight::Put => self.put.as_ref(),
+        }
+    }
+}
+
+/// The strikes of one expiration, lowest strike first.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptionExpiration {
+    /// CCYYMMDD
+    pub expiration_date: String,
+    strikes: BTreeMap<Price, OptionStrike>,
+}
+
+impl OptionExpiration {
+    pub fn strikes(&self) -> impl Iterator<Item = &OptionStrike> {
+        self.strikes.values()
+    }
+
+    pub fn strike(&self, strike: f64) -> Option<&OptionStrike> {
+        self.strikes.get(&Price(strike))
+    }
+
+    /// The strike closest to a price, eg: the at the money strike for the underlying price.
+    pub fn nearest_strike(&self, price: f64) -> Option<&OptionStrike> {
+        self.strikes.values().min_by(|a, b| (a.strike - price).abs().total_cmp(&(b.strike - price).abs()))
+    }
+}
+
+/// The options of an underlying, built from `ResponseGetInstrumentByUnderlying` (103).
+/// Instruments without a strike or put/call indicator, eg: the underlying futures, are kept in `other`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct OptionChain {
+    pub underlying_symbol: String,
+    pub exchange: String,
+    expirations: BTreeMap<String, OptionExpiration>,
+    pub other: Vec<Instrument>,
+}
+
+impl OptionChain {
+    pub fn new(underlying_symbol: String, exchange: String) -> Self {
+        Self {
+            underlying_symbol,
+            exchange,
+            ..Self::default()
+        }
+    }
+
+    /// Add an instrument to its expiration and strike, instruments that are not options go to `other`.
+    pub fn insert(&mut self, instrument: Instrument) {
+        let right = instrument.put_call_indicator.as_deref().and_then(OptionRight::from_indicator);
+        let (strike, right, expiration_date) = match (instrument.strike_price, right, &instrument.expiration_date) {
+            (Some(strike), Some(right), Some(expiration_date)) => (strike, right, expiration_date.clone()),
+            _ => {
+                self.other.push(instrument);
+                return;
+            }
+        };
+        let expiration = self.expirations.entry(expiration_date.clone()).or_insert_with(|| OptionExpiration {
+            expiration_date,
+            strikes: BTreeMap::new(),
+        });
+        let option_strike = expiration.strikes.entry(Price(strike)).or_insert_with(|| OptionStrike {
+            strike,
+            ..OptionStrike::default()
+        });
+        match right {
+            OptionRight::Call => option_strike.call = Some(instrument),
+            OptionRight::Put => option_strike.put = Some(instrument),
+        }
+    }
+
+    /// The expiration dates, nearest first.
+    pub fn expiration_dates(&self) -> Vec<&str> {
+        self.expirations.keys().map(String::as_str).collect()
+    }
+
+    pub fn expirations(&self) -> impl Iterator<Item = &OptionExpiration> {
+        self.expirations.values()
+    }
+
+    pub fn expiration(&self, expiration_date: &str) -> Option<&OptionExpiration> {
+        self.expirations.get(expiration_date)
+    }
+
+    /// Every call and put in the chain.
+    pub fn contracts(&self) -> impl Iterator<Item = &Instrument> {
+        self.expirations
+            .values()
+            .flat_map(|expiration| expiration.strikes.values())
+            .flat_map(|strike| strike.call.iter().chain(strike.put.iter()))
+    }
+}
+
+/// Request every instrument of an underlying, or of one expiration of it, on a ticker plant connection.
+pub async fn request_option_chain(
+    connection: &PlantConnection,
+    underlying_symbol: &str,
+    exchange: &str,
+    expiration_date: Option<&str>,
+    timeout: Duration,
+) -> Result<OptionChain, RithmicApiError> {
+    let request = RequestGetInstrumentByUnderlying {
+        template_id: TemplateId::RequestGetInstrumentByUnderlying.id(),
+        user_msg: vec![],
+        underlying_symbol: Some(underlying_symbol.to_string()),
+        exchange: Some(exchange.to_string()),
+        expiration_date: expiration_date.map(str::to_string),
+    };
+    let mut chain = OptionChain::new(underlying_symbol.to_string(), exchange.to_string());
+    for response in connection.request(request, timeout).await? {
+        if let Some(instrument) = Instrument::from_underlying(&response) {
+            chain.insert(instrument);
+        }
+    }
+    Ok(chain)
+}
@@ -0,0 +1,477 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use crate::connection::{ConnectionState, PlantConnection};
+use crate::errors::RithmicApiError;
+use crate::market_by_order::{Price, Side};
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::order_book::{PresenceBits, UpdateType};
+use crate::rithmic_proto_objects::rti::request_market_data_update::UpdateBits;
+use crate::rithmic_proto_objects::rti::OrderBook;
+use crate::subscriptions::{MarketDataGuard, MarketDataKey, MarketDataSubscriptions};
+
+/// Number of changes a slow receiver can fall behind before it misses some.
+const CHANGE_CHANNEL_CAPACITY: usize = 1000;
+
+/// The aggregated orders at one price.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriceLevel {
+    pub price: f64,
+    pub size: i32,
+    /// None if the exchange does not report order counts.
+    pub orders: Option<i32>,
+    /// Size implied from spread markets, None if not reported.
+    pub implied_size: Option<i32>,
+}
+
+/// A level that was added, changed or removed by an update.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LevelChange {
+    pub side: Side,
+    pub price: f64,
+    /// None if the level was removed.
+    pub level: Option<PriceLevel>,
+}
+
+/// What an `OrderBook` message did to the book.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BookChange {
+    /// The book was emptied, by `CLEAR_ORDER_BOOK`, `NO_BOOK` or a lost connection.
+    Cleared,
+    /// The book was replaced by a `SNAPSHOT_IMAGE`.
+    Snapshot,
+    /// Levels changed by a `SOLO` update or a `BEGIN` .. `END` batch.
+    Levels(Vec<LevelChange>),
+}
+
+/// A market by price book built from `OrderBook` (156) messages.
+///
+/// | `UpdateType`       | Effect                                                                      |
+/// |--------------------|-----------------------------------------------------------------------------|
+/// | `CLEAR_ORDER_BOOK` | both sides are emptied                                                      |
+/// | `NO_BOOK`          | both sides are emptied, the exchange has no book for the symbol             |
+/// | `SNAPSHOT_IMAGE`   | the book is replaced, consecutive images add to the same snapshot           |
+/// | `BEGIN`, `MIDDLE`  | levels are held until the `END` of the batch                                |
+/// | `END`              | the held levels and the levels of this message are applied together        |
+/// | `SOLO`             | the levels are applied at once                                              |
+///
+/// `PresenceBits` (`BID` = 1, `ASK` = 2) say which sides a message carries, the arrays of a side that is not present are ignored.
+/// In an update a level with a size of 0 is removed, any other size replaces the level.
+/// Updates before the first `SNAPSHOT_IMAGE`, and after the book was lost, are dropped since they only change part of the book.
+#[derive(Clone, Debug, Default)]
+pub struct PriceLevelBook {
+    bids: BTreeMap<Price, PriceLevel>,
+    asks: BTreeMap<Price, PriceLevel>,
+    /// Levels of a `BEGIN` .. `END` batch that is not complete yet.
+    batch: Vec<(Side, PriceLevel)>,
+    /// True while consecutive `SNAPSHOT_IMAGE` messages are being received.
+    in_snapshot: bool,
+    has_book: bool,
+}
+
+impl PriceLevelBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns true once a snapshot has been received, false after the book was lost or the exchange has no book.
+    /// A `CLEAR_ORDER_BOOK` empties a book that was received, the updates after it build on the empty book.
+    pub fn has_book(&self) -> bool {
+        self.has_book
+    }
+
+    /// Empty the book, eg: after the connection was lost.
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.batch.clear();
+        self.in_snapshot = false;
+        self.has_book = false;
+    }
+
+    /// Apply an `OrderBook` message, returns None if the book did not change yet, eg: for a `BEGIN` or `MIDDLE` message,
+    /// or for an update that arrives before the first snapshot.
+    pub fn apply(&mut self, message: &OrderBook) -> Option<BookChange> {
+        let update_type = message.update_type.and_then(|update_type| UpdateType::try_from(update_type).ok())?;
+        if update_type != UpdateType::SnapshotImage {
+            self.in_snapshot = false;
+        }
+        match update_type {
+            UpdateType::ClearOrderBook => {
+                let has_book = self.has_book;
+                self.clear();
+                self.has_book = has_book;
+                Some(BookChange::Cleared)
+            }
+            UpdateType::NoBook => {
+                self.clear();
+                Some(BookChange::Cleared)
+            }
+            // without a snapshot there is no book to apply the update to
+            UpdateType::Begin | UpdateType::Middle | UpdateType::End | UpdateType::Solo if !self.has_book => {
+                self.batch.clear();
+                None
+            }
+            UpdateType::SnapshotImage => {
+                if !self.in_snapshot {
+                    self.clear();
+                    self.in_snapshot = true;
+                }
+                for (side, level) in levels(message) {
+                    if level.size != 0 {
+                        self.side_mut(side).insert(Price(level.price), level);
+                    }
+                }
+                self.has_book = true;
+                Some(BookChange::Snapshot)
+            }
+            UpdateType::Begin | UpdateType::Middle => {
+                self.batch.extend(levels(message));
+                None
+            }
+            UpdateType::End => {
+                let mut batch = std::mem::take(&mut self.batch);
+                batch.extend(levels(message));
+                Some(BookChange::Levels(self.apply_levels(batch)))
+            }
+            UpdateType::Solo => Some(BookChange::Levels(self.apply_levels(levels(message)))),
+        }
+    }
+
+    fn apply_levels(&mut self, levels: Vec<(Side, PriceLevel)>) -> Vec<LevelChange> {
+        let mut changes = Vec::with_capacity(levels.len());
+        for (side, level) in levels {
+            let price = level.price;
+            let level = match level.size {
+                0 => {
+                    self.side_mut(side).remove(&Price(price));
+                    None
+                }
+                _ => {
+                    self.side_mut(side).insert(Price(price), level.clone());
+                    Some(level)
+                }
+            };
+            changes.push(LevelChange { side, price, level });
+        }
+        changes
+    }
+
+    fn side_mut(&mut self, side: Side) -> &mut BTreeMap<Price, PriceLevel> {
+        match side {
+            Side::Bid => &mut self.bids,
+            Side::Ask => &mut self.asks,
+        }
+    }
+
+    pub fn best_bid(&self) -> Option<&PriceLevel> {
+        self.bids.values().next_back()
+    }
+
+    pub fn best_ask(&self) -> Option<&PriceLevel> {
+        self.asks.values().next()
+    }
+
+    /// The best `depth` bid levels, highest price first.
+    pub fn bids(&self, depth: usize) -> Vec<PriceLevel> {
+        self.bids.values().rev().take(depth).cloned().collect()
+    }
+
+    /// The best `depth` ask levels, lowest price first.
+    pub fn asks(&self, depth: usize) -> Vec<PriceLevel> {
+        self.asks.values().take(depth).cloned().collect()
+    }
+
+    /// The level at a price, None if nothing rests there.
+    pub fn level(&self, side: Side, price: f64) -> Option<&PriceLevel> {
+        match side {
+            Side::Bid => self.bids.get(&Price(price)),
+            Side::Ask => self.asks.get(&Price(price)),
+        }
+    }
+
+    /// The size resting at a price, 0 if nothing rests there.
+    pub fn depth_at(&self, side: Side, price: f64) -> i32 {
+        self.level(side, price).map_or(0, |level| level.size)
+    }
+}
+
+/// The levels of the sides present in the message.
+fn levels(message: &OrderBook) -> Vec<(Side, PriceLevel)> {
+    // messages without presence bits carry whatever arrays are filled
+    let presence = message.presence_bits.unwrap_or(PresenceBits::Bid as u32 | PresenceBits::Ask as u32);
+    let mut levels = Vec::with_capacity(message.bid_price.len() + message.ask_price.len());
+    if presence & PresenceBits::Bid as u32 != 0 {
+        for (i, price) in message.bid_price.iter().enumerate() {
+            levels.push((Side::Bid, PriceLevel {
+                price: *price,
+                size: message.bid_size.get(i).copied().unwrap_or_default(),
+                orders: message.bid_orders.get(i).copied(),
+                implied_size: message.impl_bid_size.get(i).copied(),
+            }));
+        }
+    }
+    if presence & PresenceBits::Ask as u32 != 0 {
+        for (i, price) in message.ask_price.iter().enumerate() {
+            levels.push((Side::Ask, PriceLevel {
+                price: *price,
+                size: message.ask_size.get(i).copied().unwrap_or_default(),
+                orders: message.ask_orders.get(i).copied(),
+                implied_size: message.impl_ask_size.get(i).copied(),
+            }));
+        }
+    }
+    levels
+}
+
+/// A price level book kept current by a background task, see [`track_price_levels`].
+/// Dropping the handle stops the task and releases the `ORDER_BOOK` subscription.
+pub struct PriceLevelHandle {
+    symbol: String,
+    exchange: String,
+    book: Arc<RwLock<PriceLevelBook>>,
+    changes: broadcast::Sender<BookChange>,
+    /// Taken by `stop()`, otherwise released when the handle is dropped.
+    guard: Option<MarketDataGuard>,
+    task: JoinHandle<()>,
+}
+
+impl PriceLevelHandle {
+    pub fn symbol(&self) -> &str {
+        &self.symbol
+    }
+
+    pub fn exchange(&self) -> &str {
+        &self.exchange
+    }
+
+    /// Read the book, the lock is held while `read` runs so keep it short.
+    pub fn with_book<R>(&self, read: impl FnOnce(&PriceLevelBook) -> R) -> R {
+        read(&self.book.read().unwrap())
+    }
+
+    /// Every change applied to the book from now on.
+    pub fn changes(&self) -> broadcast::Receiver<BookChange> {
+        self.changes.subscribe()
+    }
+
+    /// Stop the task and wait for the `ORDER_BOOK` subscription to be released.
+    pub async fn stop(mut self) -> Result<(), RithmicApiError> {
+        self.task.abort();
+        match self.guard.take() {
+            Some(guard) => guard.release().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for PriceLevelHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Hold the `ORDER_BOOK` update bit of a symbol on the ticker plant and keep a book current.
+/// The server sends a new snapshot after every subscribe, the book is cleared while the plant reconnects.
+/// If another consumer already holds the bit the subscribe is sent again, so the server sends a snapshot for this book too.
+pub async fn track_price_levels(subscriptions: &MarketDataSubscriptions, symbol: String, exchange: String) -> Result<PriceLevelHandle, RithmicApiError> {
+    let connection = match subscriptions.connection().await {
+        Some(connection) => connection,
+        None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+    };
+    let book = Arc::new(RwLock::new(PriceLevelBook::new()));
+    let (changes, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+    // subscribe to the inbound messages first so the snapshot is not missed
+    let inbound = connection.subscribe();
+    let held_bits = subscriptions.update_bits(&MarketDataKey::symbol(&symbol, &exchange)).await;
+    let guard = subscriptions.subscribe(&symbol, &exchange, UpdateBits::OrderBook as u32).await?;
+    if held_bits & UpdateBits::OrderBook as u32 != 0 {
+        subscriptions.resubscribe(&symbol, &exchange, UpdateBits::OrderBook as u32).await?;
+    }
+    let task = tokio::task::spawn(run_price_levels(connection, subscriptions.clone(), symbol.clone(), exchange.clone(), inbound, book.clone(), changes.clone()));
+    Ok(PriceLevelHandle { symbol, exchange, book, changes, guard: Some(guard), task })
+}
+
+async fn run_price_levels(
+    connection: PlantConnection,
+    subscriptions: MarketDataSubscriptions,
+    symbol: String,
+    exchange: String,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    book: Arc<RwLock<PriceLevelBook>>,
+    changes: broadcast::Sender<BookChange>,
+) {
+    let mut state = connection.watch_state();
+    loop {
+        tokio::select! {
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::OrderBook(message)) => {
+                        if message.symbol.as_deref() != Some(symbol.as_str()) || message.exchange.as_deref().is_some_and(|message_exchange| message_exchange != exchange) {
+                            continue;
+                        }
+                        let change = book.write().unwrap().apply(&message);
+                        if let Some(change) = change {
+                            // no receivers is not an error
+                            let _ = changes.send(change);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Order book for {} {} lagged, skipped {} messages, subscribing again for a new snapshot", symbol, exchange, skipped);
+                        book.write().unwrap().clear();
+                        let _ = changes.send(BookChange::Cleared);
+                        if let Err(e) = subscriptions.resubscribe(&symbol, &exchange, UpdateBits::OrderBook as u32).await {
+                            eprintln!("Failed to subscribe to the order book for {} {}: {}", symbol, exchange, e);
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let current = state.borrow_and_update().clone();
+                match current {
+                    // the subscription is sent again by the connection and the server answers with a new snapshot
+                    ConnectionState::Reconnecting { .. } => {
+                        book.write().unwrap().clear();
+                        let _ = changes.send(BookChange::Cleared);
+                    }
+                    _ if current.is_terminal() => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::TemplateId;
+
+    fn message(update_type: UpdateType, bids: &[(f64, i32)], asks: &[(f64, i32)]) -> OrderBook {
+        let mut presence_bits = 0;
+        if !bids.is_empty() {
+            presence_bits |= PresenceBits::Bid as u32;
+        }
+        if !asks.is_empty() {
+            presence_bits |= PresenceBits::Ask as u32;
+        }
+        OrderBook {
+            template_id: TemplateId::OrderBook.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            presence_bits: Some(presence_bits),
+            update_type: Some(update_type as i32),
+            bid_price: bids.iter().map(|(price, _)| *price).collect(),
+            bid_size: bids.iter().map(|(_, size)| *size).collect(),
+            ask_price: asks.iter().map(|(price, _)| *price).collect(),
+            ask_size: asks.iter().map(|(_, size)| *size).collect(),
+            ..Default::default()
+        }
+    }
+
+    fn level(price: f64, size: i32) -> PriceLevel {
+        PriceLevel { price, size, orders: None, implied_size: None }
+    }
+
+    fn snapshot_book() -> PriceLevelBook {
+        let mut book = PriceLevelBook::new();
+        assert_eq!(book.apply(&message(UpdateType::SnapshotImage, &[(100.0, 5), (99.75, 8)], &[])), Some(BookChange::Snapshot));
+        assert_eq!(book.apply(&message(UpdateType::SnapshotImage, &[], &[(100.25, 4), (100.5, 6)])), Some(BookChange::Snapshot));
+        book
+    }
+
+    #[test]
+    fn snapshot_images_build_one_book() {
+        let book = snapshot_book();
+        assert!(book.has_book());
+        assert_eq!(book.best_bid(), Some(&level(100.0, 5)));
+        assert_eq!(book.best_ask(), Some(&level(100.25, 4)));
+        assert_eq!(book.bids(5), vec![level(100.0, 5), level(99.75, 8)]);
+        assert_eq!(book.asks(1), vec![level(100.25, 4)]);
+    }
+
+    #[test]
+    fn a_new_snapshot_replaces_the_book() {
+        let mut book = snapshot_book();
+        book.apply(&message(UpdateType::Solo, &[(99.5, 1)], &[]));
+        book.apply(&message(UpdateType::SnapshotImage, &[(101.0, 2)], &[(101.25, 3)]));
+        assert_eq!(book.bids(5), vec![level(101.0, 2)]);
+        assert_eq!(book.asks(5), vec![level(101.25, 3)]);
+    }
+
+    #[test]
+    fn solo_update_after_the_snapshot() {
+        let mut book = snapshot_book();
+        let change = book.apply(&message(UpdateType::Solo, &[(100.0, 7)], &[(100.0, 1)]));
+        assert_eq!(change, Some(BookChange::Levels(vec![
+            LevelChange { side: Side::Bid, price: 100.0, level: Some(level(100.0, 7)) },
+            LevelChange { side: Side::Ask, price: 100.0, level: Some(level(100.0, 1)) },
+        ])));
+        assert_eq!(book.depth_at(Side::Bid, 100.0), 7);
+        assert_eq!(book.best_ask(), Some(&level(100.0, 1)));
+    }
+
+    #[test]
+    fn size_of_zero_deletes_the_level() {
+        let mut book = snapshot_book();
+        let change = book.apply(&message(UpdateType::Solo, &[(100.0, 0)], &[]));
+        assert_eq!(change, Some(BookChange::Levels(vec![LevelChange { side: Side::Bid, price: 100.0, level: None }])));
+        assert_eq!(book.level(Side::Bid, 100.0), None);
+        assert_eq!(book.depth_at(Side::Bid, 100.0), 0);
+        assert_eq!(book.best_bid(), Some(&level(99.75, 8)));
+    }
+
+    #[test]
+    fn batch_is_applied_on_end() {
+        let mut book = snapshot_book();
+        assert_eq!(book.apply(&message(UpdateType::Begin, &[(100.0, 0)], &[])), None);
+        assert_eq!(book.apply(&message(UpdateType::Middle, &[], &[(100.25, 0)])), None);
+        assert_eq!(book.best_bid(), Some(&level(100.0, 5)));
+        let change = book.apply(&message(UpdateType::End, &[(99.5, 3)], &[]));
+        assert!(matches!(change, Some(BookChange::Levels(changes)) if changes.len() == 3));
+        assert_eq!(book.best_bid(), Some(&level(99.75, 8)));
+        assert_eq!(book.best_ask(), Some(&level(100.5, 6)));
+        assert_eq!(book.bids(5).len(), 2);
+    }
+
+    #[test]
+    fn updates_before_the_snapshot_are_dropped() {
+        let mut book = PriceLevelBook::new();
+        assert_eq!(book.apply(&message(UpdateType::Solo, &[(98.0, 1)], &[])), None);
+        assert_eq!(book.apply(&message(UpdateType::Begin, &[(97.0, 1)], &[])), None);
+        assert_eq!(book.apply(&message(UpdateType::End, &[(96.0, 1)], &[])), None);
+        assert!(!book.has_book());
+        assert_eq!(book.best_bid(), None);
+
+        book.apply(&message(UpdateType::SnapshotImage, &[(100.0, 5)], &[]));
+        assert_eq!(book.bids(5), vec![level(100.0, 5)]);
+    }
+
+    #[test]
+    fn updates_after_the_book_was_lost_are_dropped() {
+        let mut book = snapshot_book();
+        book.clear();
+        assert_eq!(book.apply(&message(UpdateType::Solo, &[(98.0, 1)], &[])), None);
+        assert_eq!(book.best_bid(), None);
+        assert!(!book.has_book());
+    }
+
+    #[test]
+    fn clear_order_book_keeps_applying_updates() {
+        let mut book = snapshot_book();
+        assert_eq!(book.apply(&message(UpdateType::ClearOrderBook, &[], &[])), Some(BookChange::Cleared));
+        assert!(book.has_book());
+        assert_eq!(book.best_bid(), None);
+        book.apply(&message(UpdateType::Solo, &[(99.0, 2)], &[]));
+        assert_eq!(book.best_bid(), Some(&level(99.0, 2)));
+
+        assert_eq!(book.apply(&message(UpdateType::NoBook, &[], &[])), Some(BookChange::Cleared));
+        assert!(!book.has_book());
+    }
+}
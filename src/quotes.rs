@@ -0,0 +1,335 @@
+use std::sync::Arc;
+use dashmap::DashMap;
+use tokio::sync::{broadcast, watch};
+use crate::connection::ConnectionState;
+use crate::market_by_order::Side;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::last_trade::TransactionType;
+use crate::rithmic_proto_objects::rti::{best_bid_offer, last_trade, BestBidOffer, LastTrade};
+
+/// The current best bid and offer of a symbol, merged from every `BestBidOffer` (151) received.
+/// A field is None until the server has sent it, or after the server cleared it.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Quote {
+    pub bid_price: Option<f64>,
+    pub bid_size: Option<i32>,
+    pub bid_orders: Option<i32>,
+    pub bid_implicit_size: Option<i32>,
+    pub bid_time: Option<String>,
+    pub ask_price: Option<f64>,
+    pub ask_size: Option<i32>,
+    pub ask_orders: Option<i32>,
+    pub ask_implicit_size: Option<i32>,
+    pub ask_time: Option<String>,
+    pub lean_price: Option<f64>,
+    pub ssboe: Option<i32>,
+    pub usecs: Option<i32>,
+}
+
+impl Quote {
+    /// Merge a `BestBidOffer` into the quote.
+    /// A snapshot replaces the quote, otherwise only the groups in `presence_bits` are updated and the groups in `clear_bits` are removed.
+    pub fn apply(&mut self, update: &BestBidOffer) {
+        if update.is_snapshot == Some(true) {
+            *self = Quote::default();
+        }
+        let presence = update.presence_bits.unwrap_or_default();
+        let clear = update.clear_bits.unwrap_or_default();
+
+        let bid = best_bid_offer::PresenceBits::Bid as u32;
+        if presence & bid != 0 {
+            self.bid_price = update.bid_price;
+            self.bid_size = update.bid_size;
+            self.bid_orders = update.bid_orders;
+            self.bid_implicit_size = update.bid_implicit_size;
+            self.bid_time = update.bid_time.clone();
+        } else if clear & bid != 0 {
+            self.bid_price = None;
+            self.bid_size = None;
+            self.bid_orders = None;
+            self.bid_implicit_size = None;
+            self.bid_time = None;
+        }
+
+        let ask = best_bid_offer::PresenceBits::Ask as u32;
+        if presence & ask != 0 {
+            self.ask_price = update.ask_price;
+            self.ask_size = update.ask_size;
+            self.ask_orders = update.ask_orders;
+            self.ask_implicit_size = update.ask_implicit_size;
+            self.ask_time = update.ask_time.clone();
+        } else if clear & ask != 0 {
+            self.ask_price = None;
+            self.ask_size = None;
+            self.ask_orders = None;
+            self.ask_implicit_size = None;
+            self.ask_time = None;
+        }
+
+        let lean_price = best_bid_offer::PresenceBits::LeanPrice as u32;
+        if presence & lean_price != 0 {
+            self.lean_price = update.lean_price;
+        } else if clear & lean_price != 0 {
+            self.lean_price = None;
+        }
+
+        if update.ssboe.is_some() {
+            self.ssboe = update.ssboe;
+            self.usecs = update.usecs;
+        }
+    }
+
+    /// The mid price, None unless both sides are known.
+    pub fn mid_price(&self) -> Option<f64> {
+        match (self.bid_price, self.ask_price) {
+            (Some(bid), Some(ask)) => Some((bid + ask) / 2.0),
+            _ => None,
+        }
+    }
+
+    pub fn spread(&self) -> Option<f64> {
+        match (self.bid_price, self.ask_price) {
+            (Some(bid), Some(ask)) => Some(ask - bid),
+            _ => None,
+        }
+    }
+}
+
+/// The last trade of a symbol and the session statistics sent with it, merged from every `LastTrade` (150) received.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Trade {
+    pub price: Option<f64>,
+    pub size: Option<i32>,
+    /// The side of the aggressor, `Side::Bid` for a buyer.
+    pub aggressor: Option<Side>,
+    pub exchange_order_id: Option<String>,
+    pub aggressor_exchange_order_id: Option<String>,
+    pub trade_time: Option<String>,
+    pub net_change: Option<f64>,
+    pub percent_change: Option<f64>,
+    pub volume: Option<u64>,
+    pub vwap: Option<f64>,
+    /// True if the last message was the snapshot sent on subscribe, not a new trade.
+    pub is_snapshot: bool,
+    pub ssboe: Option<i32>,
+    pub usecs: Option<i32>,
+}
+
+impl Trade {
+    /// Merge a `LastTrade` into the trade.
+    /// A snapshot replaces the trade, otherwise only the groups in `presence_bits` are updated and the groups in `clear_bits` are removed.
+    pub fn apply(&mut self, update: &LastTrade) {
+        let is_snapshot = update.is_snapshot == Some(true);
+        if is_snapshot {
+            *self = Trade::default();
+        }
+        self.is_snapshot = is_snapshot;
+        let presence = update.presence_bits.unwrap_or_default();
+        let clear = update.clear_bits.unwrap_or_default();
+
+        let trade = last_trade::PresenceBits::LastTrade as u32;
+        if presence & trade != 0 {
+            self.price = update.trade_price;
+            self.size = update.trade_size;
+            self.aggressor = match update.aggressor.and_then(|aggressor| TransactionType::try_from(aggressor).ok()) {
+                Some(TransactionType::Buy) => Some(Side::Bid),
+                Some(TransactionType::Sell) => Some(Side::Ask),
+                None => None,
+            };
+            self.exchange_order_id = update.exchange_order_id.clone();
+            self.aggressor_exchange_order_id = update.aggressor_exchange_order_id.clone();
+            self.trade_time = update.trade_time.clone();
+        } else if clear & trade != 0 {
+            self.price = None;
+            self.size = None;
+            self.aggressor = None;
+            self.exchange_order_id = None;
+            self.aggressor_exchange_order_id = None;
+            self.trade_time = None;
+        }
+
+        merge(&mut self.net_change, update.net_change, presence, clear, last_trade::PresenceBits::NetChange as u32);
+        merge(&mut self.percent_change, update.percent_change, presence, clear, last_trade::PresenceBits::PrecentChange as u32);
+        merge(&mut self.volume, update.volume, presence, clear, last_trade::PresenceBits::Volume as u32);
+        merge(&mut self.vwap, update.vwap, presence, clear, last_trade::PresenceBits::Vwap as u32);
+
+        if update.ssboe.is_some() {
+            self.ssboe = update.ssboe;
+            self.usecs = update.usecs;
+        }
+    }
+}
+
+fn merge<T>(field: &mut Option<T>, value: Option<T>, presence: u32, clear: u32, bit: u32) {
+    if presence & bit != 0 {
+        *field = value;
+    } else if clear & bit != 0 {
+        *field = None;
+    }
+}
+
+/// None until the first message for the symbol arrives.
+struct QuoteChannels {
+    quote: watch::Sender<Option<Quote>>,
+    trade: watch::Sender<Option<Trade>>,
+}
+
+impl QuoteChannels {
+    fn new() -> Self {
+        Self {
+            quote: watch::channel(None).0,
+            trade: watch::channel(None).0,
+        }
+    }
+}
+
+/// The latest quote and trade of every symbol, keyed by (symbol, exchange).
+/// Filled from the `BestBidOffer` and `LastTrade` messages of the ticker plant, subscribe to them with `RequestMarketDataUpdate`.
+/// Get a receiver once and read it with `borrow()`, reads do not touch the cache.
+/// Cloning the cache gives another handle to the same quotes.
+#[derive(Clone, Default)]
+pub struct QuoteCache {
+    symbols: Arc<DashMap<(String, String), QuoteChannels>>,
+}
+
+impl QuoteCache {
+    /// The latest quote of the symbol, None until the first `BestBidOffer` arrives, the receiver sees every update from now on.
+    pub fn quotes(&self, symbol: &str, exchange: &str) -> watch::Receiver<Option<Quote>> {
+        self.channels(symbol, exchange, |channels| channels.quote.subscribe())
+    }
+
+    /// The latest trade of the symbol, None until the first `LastTrade` arrives, the receiver sees every update from now on.
+    pub fn trades(&self, symbol: &str, exchange: &str) -> watch::Receiver<Option<Trade>> {
+        self.channels(symbol, exchange, |channels| channels.trade.subscribe())
+    }
+
+    /// A copy of the latest quote, None if nothing was received for the symbol yet.
+    pub fn quote(&self, symbol: &str, exchange: &str) -> Option<Quote> {
+        self.symbols.get(&(symbol.to_string(), exchange.to_string())).and_then(|channels| channels.quote.borrow().clone())
+    }
+
+    /// A copy of the latest trade, None if nothing was received for the symbol yet.
+    pub fn trade(&self, symbol: &str, exchange: &str) -> Option<Trade> {
+        self.symbols.get(&(symbol.to_string(), exchange.to_string())).and_then(|channels| channels.trade.borrow().clone())
+    }
+
+    /// Every (symbol, exchange) in the cache.
+    pub fn symbols(&self) -> Vec<(String, String)> {
+        self.symbols.iter().map(|channels| channels.key().clone()).collect()
+    }
+
+    /// Set every quote and trade back to None, eg: while the ticker plant is reconnecting, so a quote from before the outage is not read as live.
+    /// The server sends a snapshot for every subscription once it is connected again.
+    pub fn clear(&self) {
+        for channels in self.symbols.iter() {
+            channels.quote.send_if_modified(|quote| quote.take().is_some());
+            channels.trade.send_if_modified(|trade| trade.take().is_some());
+        }
+    }
+
+    pub fn apply_best_bid_offer(&self, update: &BestBidOffer) {
+        let (symbol, exchange) = match (&update.symbol, &update.exchange) {
+            (Some(symbol), Some(exchange)) => (symbol, exchange),
+            _ => return,
+        };
+        self.channels(symbol, exchange, |channels| channels.quote.send_modify(|quote| quote.get_or_insert_with(Quote::default).apply(update)));
+    }
+
+    pub fn apply_last_trade(&self, update: &LastTrade) {
+        let (symbol, exchange) = match (&update.symbol, &update.exchange) {
+            (Some(symbol), Some(exchange)) => (symbol, exchange),
+            _ => return,
+        };
+        self.channels(symbol, exchange, |channels| channels.trade.send_modify(|trade| trade.get_or_insert_with(Trade::default).apply(update)));
+    }
+
+    fn channels<R>(&self, symbol: &str, exchange: &str, use_channels: impl FnOnce(&QuoteChannels) -> R) -> R {
+        let key = (symbol.to_string(), exchange.to_string());
+        if let Some(channels) = self.symbols.get(&key) {
+            return use_channels(&channels);
+        }
+        let channels = self.symbols.entry(key).or_insert_with(QuoteChannels::new);
+        use_channels(&channels)
+    }
+}
+
+/// Keep the cache current with the messages of a plant connection until the connection ends.
+/// The cache is cleared while the connection is reconnecting and when it ends, since no updates arrive meanwhile.
+pub(crate) async fn track_quotes(
+    cache: QuoteCache,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    mut state: watch::Receiver<ConnectionState>,
+) {
+    loop {
+        tokio::select! {
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::BestBidOffer(update)) => cache.apply_best_bid_offer(&update),
+                    Ok(RithmicMessage::LastTrade(update)) => cache.apply_last_trade(&update),
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Quote cache lagged, skipped {} messages", skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    cache.clear();
+                    return;
+                }
+                let current = state.borrow_and_update().clone();
+                match current {
+                    ConnectionState::Reconnecting { .. } => cache.clear(),
+                    _ if current.is_terminal() => {
+                        cache.clear();
+                        return;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::templates::TemplateId;
+
+    fn best_bid_offer(bid: f64, ask: f64) -> BestBidOffer {
+        BestBidOffer {
+            template_id: TemplateId::BestBidOffer.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            presence_bits: Some(best_bid_offer::PresenceBits::Bid as u32 | best_bid_offer::PresenceBits::Ask as u32),
+            bid_price: Some(bid),
+            ask_price: Some(ask),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn quote_is_none_until_the_first_message() {
+        let cache = QuoteCache::default();
+        let quotes = cache.quotes("ESZ4", "CME");
+        assert_eq!(*quotes.borrow(), None);
+        cache.apply_best_bid_offer(&best_bid_offer(100.0, 100.5));
+        assert_eq!(quotes.borrow().as_ref().and_then(Quote::mid_price), Some(100.25));
+    }
+
+    #[test]
+    fn clear_sets_every_quote_back_to_none() {
+        let cache = QuoteCache::default();
+        cache.apply_best_bid_offer(&best_bid_offer(100.0, 100.5));
+        let mut quotes = cache.quotes("ESZ4", "CME");
+        cache.clear();
+        assert!(quotes.has_changed().unwrap());
+        assert_eq!(*quotes.borrow_and_update(), None);
+        assert_eq!(cache.quote("ESZ4", "CME"), None);
+
+        // nothing changes for a receiver when the quote was already cleared
+        cache.clear();
+        assert!(!quotes.has_changed().unwrap());
+    }
+}
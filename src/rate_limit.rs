@@ -0,0 +1,305 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use crate::errors::RithmicApiError;
+use crate::templates::TemplateId;
+
+/// Groups of requests that get their own budget.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RequestClass {
+    /// New, modify and cancel requests for orders and brackets.
+    OrderAction,
+    /// Bar replays, order history and execution replays.
+    HistoryReplay,
+    /// Market data, order update, PnL and bar subscriptions.
+    Subscription,
+    /// Everything else, only limited by the plant budget.
+    Other,
+}
+
+impl RequestClass {
+    pub fn from_template_id(template_id: i32) -> RequestClass {
+        match TemplateId::from_i32(template_id) {
+            Some(TemplateId::RequestNewOrder)
+            | Some(TemplateId::RequestModifyOrder)
+            | Some(TemplateId::RequestCancelOrder)
+            | Some(TemplateId::RequestOcoOrder)
+            | Some(TemplateId::RequestBracketOrder)
+            | Some(TemplateId::RequestUpdateTargetBracketLevel)
+            | Some(TemplateId::RequestUpdateStopBracketLevel)
+            | Some(TemplateId::RequestLinkOrders)
+            | Some(TemplateId::RequestCancelAllOrders)
+            | Some(TemplateId::RequestModifyOrderReferenceData)
+            | Some(TemplateId::RequestExitPosition) => RequestClass::OrderAction,
+
+            Some(TemplateId::RequestTimeBarReplay)
+            | Some(TemplateId::RequestTickBarReplay)
+            | Some(TemplateId::RequestVolumeProfileMinuteBars)
+            | Some(TemplateId::RequestResumeBars)
+            | Some(TemplateId::RequestShowOrderHistory)
+            | Some(TemplateId::RequestShowOrderHistorySummary)
+            | Some(TemplateId::RequestShowOrderHistoryDetail)
+            | Some(TemplateId::RequestReplayExecutions) => RequestClass::HistoryReplay,
+
+            Some(TemplateId::RequestMarketDataUpdate)
+            | Some(TemplateId::RequestMarketDataUpdateByUnderlying)
+            | Some(TemplateId::RequestDepthByOrderUpdates)
+            | Some(TemplateId::RequestTimeBarUpdate)
+            | Some(TemplateId::RequestTickBarUpdate)
+            | Some(TemplateId::RequestSubscribeForOrderUpdates)
+            | Some(TemplateId::RequestSubscribeToBracketUpdates)
+            | Some(TemplateId::RequestPnLPositionUpdates)
+            | Some(TemplateId::RequestAccountRmsUpdates) => RequestClass::Subscription,
+
+            _ => RequestClass::Other,
+        }
+    }
+}
+
+/// What happens to a request when its budget is used up.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitMode {
+    /// `send` returns straight away and the socket task holds the request until the budget allows it, later requests wait behind it.
+    Queue,
+    /// `send` waits until the budget allows the request.
+    Delay,
+    /// `send` fails with [`crate::errors::RithmicApiError::RateLimited`].
+    Reject,
+}
+
+/// A token bucket, `burst` requests can be sent at once and the budget refills at `per_second`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RateLimit {
+    pub burst: u32,
+    pub per_second: f64,
+}
+
+impl RateLimit {
+    pub fn new(burst: u32, per_second: f64) -> Self {
+        Self { burst, per_second }
+    }
+}
+
+/// Budgets applied to each plant connection, every plant gets its own buckets.
+/// A request must fit both the plant budget and the budget of its [`RequestClass`].
+#[derive(Clone, Debug)]
+pub struct RateLimitConfig {
+    pub mode: RateLimitMode,
+    /// Budget for every request sent on a plant, None for no limit.
+    pub plant: Option<RateLimit>,
+    /// Budget per request class, a class without an entry is only limited by the plant budget.
+    pub classes: HashMap<RequestClass, RateLimit>,
+}
+
+impl RateLimitConfig {
+    /// Every budget must hold and refill at least one token, a `burst` of zero or a `per_second` of zero or less would hold its requests forever.
+    pub fn validate(&self) -> Result<(), RithmicApiError> {
+        let plant = self.plant.iter().map(|limit| ("plant".to_string(), limit));
+        let classes = self.classes.iter().map(|(class, limit)| (format!("{:?}", class), limit));
+        for (name, limit) in plant.chain(classes) {
+            if limit.burst < 1 {
+                return Err(RithmicApiError::InvalidConfig(format!("Rate limit burst for {} must be at least 1: {}", name, limit.burst)));
+            }
+            if limit.per_second.is_nan() || limit.per_second <= 0.0 {
+                return Err(RithmicApiError::InvalidConfig(format!("Rate limit per_second for {} must be greater than 0: {}", name, limit.per_second)));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            mode: RateLimitMode::Delay,
+            plant: Some(RateLimit::new(50, 25.0)),
+            classes: HashMap::from([
+                (RequestClass::OrderAction, RateLimit::new(20, 10.0)),
+                (RequestClass::HistoryReplay, RateLimit::new(5, 1.0)),
+                (RequestClass::Subscription, RateLimit::new(50, 20.0)),
+            ]),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    limit: RateLimit,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            tokens: limit.burst as f64,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.limit.per_second).min(self.limit.burst as f64);
+        self.updated = now;
+    }
+
+    /// How long until a token is available, zero if one is available now.
+    fn wait_time(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        if self.limit.per_second <= 0.0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.limit.per_second)
+    }
+}
+
+/// The buckets of one plant connection.
+#[derive(Debug)]
+pub(crate) struct RateLimiter {
+    mode: RateLimitMode,
+    plant: Option<Mutex<TokenBucket>>,
+    classes: HashMap<RequestClass, Mutex<TokenBucket>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new(config: &RateLimitConfig) -> Self {
+        Self {
+            mode: config.mode,
+            plant: config.plant.map(|limit| Mutex::new(TokenBucket::new(limit))),
+            classes: config.classes.iter().map(|(class, limit)| (*class, Mutex::new(TokenBucket::new(*limit)))).collect(),
+        }
+    }
+
+    pub(crate) fn mode(&self) -> RateLimitMode {
+        self.mode
+    }
+
+    /// Take a token from the plant bucket and the class bucket.
+    /// Nothing is taken unless both have a token, otherwise returns how long to wait before trying again.
+    pub(crate) fn try_acquire(&self, class: RequestClass) -> Result<(), Duration> {
+        let now = Instant::now();
+        // always locked in the same order, plant then class
+        let mut plant = self.plant.as_ref().map(|bucket| bucket.lock().unwrap());
+        let mut class = self.classes.get(&class).map(|bucket| bucket.lock().unwrap());
+
+        let mut wait = Duration::ZERO;
+        for bucket in plant.iter_mut().chain(class.iter_mut()) {
+            bucket.refill(now);
+            wait = wait.max(bucket.wait_time());
+        }
+        if wait > Duration::ZERO {
+            return Err(wait);
+        }
+        for bucket in plant.iter_mut().chain(class.iter_mut()) {
+            bucket.tokens -= 1.0;
+        }
+        Ok(())
+    }
+
+    /// Wait until a token is available in both buckets and take it.
+    pub(crate) async fn acquire(&self, class: RequestClass) {
+        while let Err(wait) = self.try_acquire(class) {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(plant: Option<RateLimit>, classes: &[(RequestClass, RateLimit)]) -> RateLimitConfig {
+        RateLimitConfig {
+            mode: RateLimitMode::Reject,
+            plant,
+            classes: classes.iter().cloned().collect(),
+        }
+    }
+
+    #[test]
+    fn validate_rejects_a_budget_that_never_refills() {
+        assert!(RateLimitConfig::default().validate().is_ok());
+        for per_second in [0.0, -1.0, f64::NAN] {
+            let plant = config(Some(RateLimit::new(1, per_second)), &[]);
+            assert!(matches!(plant.validate(), Err(RithmicApiError::InvalidConfig(_))));
+            let class = config(None, &[(RequestClass::OrderAction, RateLimit::new(1, per_second))]);
+            assert!(matches!(class.validate(), Err(RithmicApiError::InvalidConfig(_))));
+        }
+    }
+
+    #[test]
+    fn validate_rejects_a_budget_without_capacity() {
+        let plant = config(Some(RateLimit::new(0, 1.0)), &[]);
+        assert!(matches!(plant.validate(), Err(RithmicApiError::InvalidConfig(_))));
+        let class = config(None, &[(RequestClass::Subscription, RateLimit::new(0, 1.0))]);
+        assert!(matches!(class.validate(), Err(RithmicApiError::InvalidConfig(_))));
+        assert!(config(Some(RateLimit::new(1, 1.0)), &[]).validate().is_ok());
+    }
+
+    #[test]
+    fn wait_time_is_the_time_to_refill_one_token() {
+        let mut bucket = TokenBucket::new(RateLimit::new(2, 4.0));
+        assert_eq!(bucket.wait_time(), Duration::ZERO);
+        bucket.tokens = 0.5;
+        assert_eq!(bucket.wait_time(), Duration::from_millis(125));
+    }
+
+    #[test]
+    fn wait_time_is_max_when_the_bucket_never_refills() {
+        let mut bucket = TokenBucket::new(RateLimit::new(1, 0.0));
+        bucket.tokens = 0.0;
+        assert_eq!(bucket.wait_time(), Duration::MAX);
+    }
+
+    #[test]
+    fn refill_is_capped_at_burst() {
+        let mut bucket = TokenBucket::new(RateLimit::new(3, 10.0));
+        bucket.tokens = 0.0;
+        let now = bucket.updated + Duration::from_millis(100);
+        bucket.refill(now);
+        assert!((bucket.tokens - 1.0).abs() < 1e-9);
+        bucket.refill(now + Duration::from_secs(10));
+        assert_eq!(bucket.tokens, 3.0);
+    }
+
+    #[test]
+    fn try_acquire_allows_the_burst_then_waits() {
+        let limiter = RateLimiter::new(&config(Some(RateLimit::new(2, 1.0)), &[]));
+        assert!(limiter.try_acquire(RequestClass::Other).is_ok());
+        assert!(limiter.try_acquire(RequestClass::Other).is_ok());
+        let wait = limiter.try_acquire(RequestClass::Other).unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn try_acquire_takes_nothing_unless_both_buckets_have_a_token() {
+        let limiter = RateLimiter::new(&config(
+            Some(RateLimit::new(2, 1.0)),
+            &[(RequestClass::OrderAction, RateLimit::new(1, 1.0))],
+        ));
+        assert!(limiter.try_acquire(RequestClass::OrderAction).is_ok());
+        // the class bucket is empty, so the plant token is not taken
+        assert!(limiter.try_acquire(RequestClass::OrderAction).is_err());
+        assert!(limiter.try_acquire(RequestClass::Other).is_ok());
+        assert!(limiter.try_acquire(RequestClass::Other).is_err());
+    }
+
+    #[test]
+    fn try_acquire_is_unlimited_without_buckets() {
+        let limiter = RateLimiter::new(&config(None, &[]));
+        for _ in 0..1000 {
+            assert!(limiter.try_acquire(RequestClass::HistoryReplay).is_ok());
+        }
+    }
+
+    #[test]
+    fn from_template_id_groups_requests() {
+        assert_eq!(RequestClass::from_template_id(TemplateId::RequestNewOrder.id()), RequestClass::OrderAction);
+        assert_eq!(RequestClass::from_template_id(TemplateId::RequestTimeBarReplay.id()), RequestClass::HistoryReplay);
+        assert_eq!(RequestClass::from_template_id(TemplateId::RequestMarketDataUpdate.id()), RequestClass::Subscription);
+        assert_eq!(RequestClass::from_template_id(TemplateId::RequestHeartbeat.id()), RequestClass::Other);
+    }
+}
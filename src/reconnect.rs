@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::request_market_data_update;
+use crate::rithmic_proto_objects::rti::request_market_data_update_by_underlying;
+use crate::rithmic_proto_objects::rti::request_depth_by_order_updates;
+use crate::rithmic_proto_objects::rti::request_pn_l_position_updates;
+use crate::rithmic_proto_objects::rti::request_time_bar_update;
+use crate::rithmic_proto_objects::rti::request_tick_bar_update;
+use crate::templates::TemplateId;
+
+/// Exponential backoff used when a plant connection is lost.
+#[derive(Clone, Debug)]
+pub struct ReconnectPolicy {
+    /// Delay before the first attempt.
+    pub initial_delay: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_delay: Duration,
+    /// Each failed attempt multiplies the delay by this factor.
+    pub multiplier: f64,
+    /// Fraction of the delay that is randomised, 0.2 gives a delay within +/- 20%, so several plants do not reconnect in lock step.
+    pub jitter: f64,
+    /// Give up after this many failed attempts, None retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            multiplier: 2.0,
+            jitter: 0.2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// The delay before the given attempt, attempts start at 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(32) as i32;
+        let base = self.initial_delay.as_secs_f64() * self.multiplier.max(1.0).powi(exponent);
+        let base = base.min(self.max_delay.as_secs_f64());
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        // random value in -1.0..=1.0
+        let random = (RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64) * 2.0 - 1.0;
+        Duration::from_secs_f64((base * (1.0 + jitter * random)).max(0.0))
+    }
+
+    /// Returns true if another attempt is allowed after `attempt` failures.
+    pub fn should_retry(&self, attempt: u32) -> bool {
+        match self.max_attempts {
+            Some(max_attempts) => attempt < max_attempts,
+            None => true,
+        }
+    }
+}
+
+/// The subscriptions sent on a plant connection, so they can be sent again after a reconnect.
+/// Market data update bits are merged per symbol, so the replayed request covers every bit still subscribed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SubscriptionRegistry {
+    subscriptions: BTreeMap<(TemplateId, String), RithmicMessage>,
+}
+
+impl SubscriptionRegistry {
+    /// Returns true if requests with this template change the subscription state.
+    pub(crate) fn is_tracked(template_id: i32) -> bool {
+        matches!(
+            TemplateId::from_i32(template_id),
+            Some(TemplateId::RequestMarketDataUpdate)
+                | Some(TemplateId::RequestMarketDataUpdateByUnderlying)
+                | Some(TemplateId::RequestDepthByOrderUpdates)
+                | Some(TemplateId::RequestSubscribeForOrderUpdates)
+                | Some(TemplateId::RequestSubscribeToBracketUpdates)
+                | Some(TemplateId::RequestPnLPositionUpdates)
+                | Some(TemplateId::RequestTimeBarUpdate)
+                | Some(TemplateId::RequestTickBarUpdate)
+        )
+    }
+
+    /// Update the registry with a request that was sent.
+    /// The `user_msg` is dropped, a replayed subscription is not an answer to the request that was first sent.
+    pub(crate) fn record(&mut self, mut message: RithmicMessage) {
+        clear_user_msg(&mut message);
+        let template = message.template();
+        match message {
+            RithmicMessage::RequestMarketDataUpdate(mut request) => {
+                let key = (template, key(&[&request.symbol, &request.exchange]));
+                let subscribe = request.request == Some(request_market_data_update::Request::Subscribe as i32);
+                let existing = match self.subscriptions.get(&key) {
+                    Some(RithmicMessage::RequestMarketDataUpdate(existing)) => existing.update_bits.unwrap_or_default(),
+                    _ => 0,
+                };
+                let bits = merge_bits(existing, request.update_bits, subscribe);
+                if bits == 0 {
+                    self.subscriptions.remove(&key);
+                } else {
+                    request.update_bits = Some(bits);
+                    request.request = Some(request_market_data_update::Request::Subscribe as i32);
+                    self.subscriptions.insert(key, RithmicMessage::RequestMarketDataUpdate(request));
+                }
+            }
+            RithmicMessage::RequestMarketDataUpdateByUnderlying(mut request) => {
+                let key = (template, key(&[&request.underlying_symbol, &request.exchange, &request.expiration_date]));
+                let subscribe = request.request == Some(request_market_data_update_by_underlying::Request::Subscribe as i32);
+                let existing = match self.subscriptions.get(&key) {
+                    Some(RithmicMessage::RequestMarketDataUpdateByUnderlying(existing)) => existing.update_bits.unwrap_or_default(),
+                    _ => 0,
+                };
+                let bits = merge_bits(existing, request.update_bits, subscribe);
+                if bits == 0 {
+                    self.subscriptions.remove(&key);
+                } else {
+                    request.update_bits = Some(bits);
+                    request.request = Some(request_market_data_update_by_underlying::Request::Subscribe as i32);
+                    self.subscriptions.insert(key, RithmicMessage::RequestMarketDataUpdateByUnderlying(request));
+                }
+            }
+            RithmicMessage::RequestDepthByOrderUpdates(ref request) => {
+                let depth_price = request.depth_price.map(|price| price.to_string());
+                let key = (template, key(&[&request.symbol, &request.exchange, &depth_price]));
+                let subscribe = request.request == Some(request_depth_by_order_updates::Request::Subscribe as i32);
+                self.insert_or_remove(key, message, subscribe);
+            }
+            RithmicMessage::RequestSubscribeForOrderUpdates(ref request) => {
+                let key = (template, key(&[&request.fcm_id, &request.ib_id, &request.account_id]));
+                self.insert_or_remove(key, message, true);
+            }
+            RithmicMessage::RequestSubscribeToBracketUpdates(ref request) => {
+                let key = (template, key(&[&request.fcm_id, &request.ib_id, &request.account_id]));
+                self.insert_or_remove(key, message, true);
+            }
+            RithmicMessage::RequestPnLPositionUpdates(ref request) => {
+                let key = (template, key(&[&request.fcm_id, &request.ib_id, &request.account_id]));
+                let subscribe = request.request == Some(request_pn_l_position_updates::Request::Subscribe as i32);
+                self.insert_or_remove(key, message, subscribe);
+            }
+            RithmicMessage::RequestTimeBarUpdate(ref request) => {
+                let bar_type = request.bar_type.map(|bar_type| bar_type.to_string());
+                let period = request.bar_type_period.map(|period| period.to_string());
+                let key = (template, key(&[&request.symbol, &request.exchange, &bar_type, &period]));
+                let subscribe = request.request == Some(request_time_bar_update::Request::Subscribe as i32);
+                self.insert_or_remove(key, message, subscribe);
+            }
+            RithmicMessage::RequestTickBarUpdate(ref request) => {
+                let bar_type = request.bar_type.map(|bar_type| bar_type.to_string());
+                let bar_sub_type = request.bar_sub_type.map(|bar_sub_type| bar_sub_type.to_string());
+                let key = (template, key(&[&request.symbol, &request.exchange, &bar_type, &bar_sub_type, &request.bar_type_specifier]));
+                let subscribe = request.request == Some(request_tick_bar_update::Request::Subscribe as i32);
+                self.insert_or_remove(key, message, subscribe);
+            }
+            _ => {}
+        }
+    }
+
+    /// Every active subscription, in a stable order.
+    pub(crate) fn replay(&self) -> Vec<RithmicMessage> {
+        self.subscriptions.values().cloned().collect()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.subscriptions.len()
+    }
+
+    fn insert_or_remove(&mut self, key: (TemplateId, String), message: RithmicMessage, subscribe: bool) {
+        match subscribe {
+            true => { self.subscriptions.insert(key, message); }
+            false => { self.subscriptions.remove(&key); }
+        }
+    }
+}
+
+fn clear_user_msg(message: &mut RithmicMessage) {
+    match message {
+        RithmicMessage::RequestMarketDataUpdate(request) => request.user_msg.clear(),
+        RithmicMessage::RequestMarketDataUpdateByUnderlying(request) => request.user_msg.clear(),
+        RithmicMessage::RequestDepthByOrderUpdates(request) => request.user_msg.clear(),
+        RithmicMessage::RequestSubscribeForOrderUpdates(request) => request.user_msg.clear(),
+        RithmicMessage::RequestSubscribeToBracketUpdates(request) => request.user_msg.clear(),
+        RithmicMessage::RequestPnLPositionUpdates(request) => request.user_msg.clear(),
+        RithmicMessage::RequestTimeBarUpdate(request) => request.user_msg.clear(),
+        RithmicMessage::RequestTickBarUpdate(request) => request.user_msg.clear(),
+        _ => {}
+    }
+}
+
+fn key(fields: &[&Option<String>]) -> String {
+    fields
+        .iter()
+        .map(|field| field.as_deref().unwrap_or_default())
+        .collect::<Vec<&str>>()
+        .join("|")
+}
+
+/// An unsubscribe without update bits removes every bit.
+fn merge_bits(existing: u32, update_bits: Option<u32>, subscribe: bool) -> u32 {
+    match (subscribe, update_bits) {
+        (true, Some(bits)) => existing | bits,
+        (true, None) => existing,
+        (false, Some(bits)) => existing & !bits,
+        (false, None) => 0,
+    }
+}
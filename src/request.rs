@@ -0,0 +1,138 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use prost::{Message as ProstMessage};
+use crate::messages::RithmicMessage;
+use crate::templates::TemplateId;
+use crate::rithmic_proto_objects::rti::{
+    RequestAcceptAgreement, RequestAccountList, RequestAccountRmsInfo, RequestAccountRmsUpdates,
+    RequestAuxilliaryReferenceData, RequestBracketOrder, RequestCancelAllOrders, RequestCancelOrder,
+    RequestDepthByOrderSnapshot, RequestDepthByOrderUpdates, RequestEasyToBorrowList, RequestExitPosition,
+    RequestFrontMonthContract, RequestGetInstrumentByUnderlying, RequestGetVolumeAtPrice, RequestGiveTickSizeTypeTable,
+    RequestHeartbeat, RequestLinkOrders, RequestListAcceptedAgreements, RequestListExchangePermissions,
+    RequestListUnacceptedAgreements, RequestLogin, RequestLoginInfo, RequestLogout, RequestMarketDataUpdate,
+    RequestMarketDataUpdateByUnderlying, RequestModifyOrder, RequestModifyOrderReferenceData, RequestNewOrder,
+    RequestOcoOrder, RequestOrderSessionConfig, RequestPnLPositionSnapshot, RequestPnLPositionUpdates,
+    RequestProductCodes, RequestProductRmsInfo, RequestReferenceData, RequestReplayExecutions, RequestResumeBars,
+    RequestRithmicSystemGatewayInfo, RequestRithmicSystemInfo, RequestSearchSymbols,
+    RequestSetRithmicMrktDataSelfCertStatus, RequestShowAgreement, RequestShowBracketStops, RequestShowBrackets,
+    RequestShowOrderHistory, RequestShowOrderHistoryDates, RequestShowOrderHistoryDetail,
+    RequestShowOrderHistorySummary, RequestShowOrders, RequestSubscribeForOrderUpdates,
+    RequestSubscribeToBracketUpdates, RequestTickBarReplay, RequestTickBarUpdate, RequestTimeBarReplay,
+    RequestTimeBarUpdate, RequestTradeRoutes, RequestUpdateStopBracketLevel, RequestUpdateTargetBracketLevel,
+    RequestVolumeProfileMinuteBars, ResponseAcceptAgreement, ResponseAccountList, ResponseAccountRmsInfo,
+    ResponseAccountRmsUpdates, ResponseAuxilliaryReferenceData, ResponseBracketOrder, ResponseCancelAllOrders,
+    ResponseCancelOrder, ResponseDepthByOrderSnapshot, ResponseDepthByOrderUpdates, ResponseEasyToBorrowList,
+    ResponseExitPosition, ResponseFrontMonthContract, ResponseGetInstrumentByUnderlying, ResponseGetVolumeAtPrice,
+    ResponseGiveTickSizeTypeTable, ResponseHeartbeat, ResponseLinkOrders, ResponseListAcceptedAgreements,
+    ResponseListExchangePermissions, ResponseListUnacceptedAgreements, ResponseLogin, ResponseLoginInfo,
+    ResponseLogout, ResponseMarketDataUpdate, ResponseMarketDataUpdateByUnderlying, ResponseModifyOrder,
+    ResponseModifyOrderReferenceData, ResponseNewOrder, ResponseOcoOrder, ResponseOrderSessionConfig,
+    ResponsePnLPositionSnapshot, ResponsePnLPositionUpdates, ResponseProductCodes, ResponseProductRmsInfo,
+    ResponseReferenceData, ResponseReplayExecutions, ResponseResumeBars, ResponseRithmicSystemGatewayInfo,
+    ResponseRithmicSystemInfo, ResponseSearchSymbols, ResponseSetRithmicMrktDataSelfCertStatus, ResponseShowAgreement,
+    ResponseShowBracketStops, ResponseShowBrackets, ResponseShowOrderHistory, ResponseShowOrderHistoryDates,
+    ResponseShowOrderHistoryDetail, ResponseShowOrderHistorySummary, ResponseShowOrders,
+    ResponseSubscribeForOrderUpdates, ResponseSubscribeToBracketUpdates, ResponseTickBarReplay, ResponseTickBarUpdate,
+    ResponseTimeBarReplay, ResponseTimeBarUpdate, ResponseTradeRoutes, ResponseUpdateStopBracketLevel,
+    ResponseUpdateTargetBracketLevel, ResponseVolumeProfileMinuteBars
+};
+
+/// Prefix of the token added to `user_msg` to match responses to the request that caused them.
+pub const CORRELATION_PREFIX: &str = "ff_rithmic_api#";
+
+static NEXT_CORRELATION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A unique token for one request, rithmic echoes every `user_msg` string back in the response.
+pub(crate) fn next_correlation_token() -> String {
+    format!("{}{}", CORRELATION_PREFIX, NEXT_CORRELATION_ID.fetch_add(1, Ordering::Relaxed))
+}
+
+/// A request that the server answers with one or more responses of a known type.
+/// Implemented for every generated request type, see [`crate::connection::PlantConnection::request`].
+pub trait RithmicRequest: ProstMessage + Send + Sync + 'static {
+    /// The response type, for `RequestGetInstrumentByUnderlying` the keys response (104) is not included.
+    type Response: TryFrom<RithmicMessage> + Send + 'static;
+
+    /// The template of the request.
+    const TEMPLATE_ID: TemplateId;
+
+    /// The `user_msg` field, used to stamp the correlation token.
+    fn user_msg_mut(&mut self) -> &mut Vec<String>;
+}
+
+macro_rules! rithmic_requests {
+    ($($request:ident => $response:ident),* $(,)?) => {
+        $(
+        impl RithmicRequest for $request {
+            type Response = $response;
+            const TEMPLATE_ID: TemplateId = TemplateId::$request;
+
+            fn user_msg_mut(&mut self) -> &mut Vec<String> {
+                &mut self.user_msg
+            }
+        }
+        )*
+    };
+}
+
+rithmic_requests! {
+    RequestLogin => ResponseLogin,
+    RequestLogout => ResponseLogout,
+    RequestReferenceData => ResponseReferenceData,
+    RequestRithmicSystemInfo => ResponseRithmicSystemInfo,
+    RequestHeartbeat => ResponseHeartbeat,
+    RequestRithmicSystemGatewayInfo => ResponseRithmicSystemGatewayInfo,
+    RequestMarketDataUpdate => ResponseMarketDataUpdate,
+    RequestGetInstrumentByUnderlying => ResponseGetInstrumentByUnderlying,
+    RequestMarketDataUpdateByUnderlying => ResponseMarketDataUpdateByUnderlying,
+    RequestGiveTickSizeTypeTable => ResponseGiveTickSizeTypeTable,
+    RequestSearchSymbols => ResponseSearchSymbols,
+    RequestProductCodes => ResponseProductCodes,
+    RequestFrontMonthContract => ResponseFrontMonthContract,
+    RequestDepthByOrderSnapshot => ResponseDepthByOrderSnapshot,
+    RequestDepthByOrderUpdates => ResponseDepthByOrderUpdates,
+    RequestGetVolumeAtPrice => ResponseGetVolumeAtPrice,
+    RequestAuxilliaryReferenceData => ResponseAuxilliaryReferenceData,
+    RequestTimeBarUpdate => ResponseTimeBarUpdate,
+    RequestTimeBarReplay => ResponseTimeBarReplay,
+    RequestTickBarUpdate => ResponseTickBarUpdate,
+    RequestTickBarReplay => ResponseTickBarReplay,
+    RequestVolumeProfileMinuteBars => ResponseVolumeProfileMinuteBars,
+    RequestResumeBars => ResponseResumeBars,
+    RequestLoginInfo => ResponseLoginInfo,
+    RequestAccountList => ResponseAccountList,
+    RequestAccountRmsInfo => ResponseAccountRmsInfo,
+    RequestProductRmsInfo => ResponseProductRmsInfo,
+    RequestSubscribeForOrderUpdates => ResponseSubscribeForOrderUpdates,
+    RequestTradeRoutes => ResponseTradeRoutes,
+    RequestNewOrder => ResponseNewOrder,
+    RequestModifyOrder => ResponseModifyOrder,
+    RequestCancelOrder => ResponseCancelOrder,
+    RequestShowOrderHistoryDates => ResponseShowOrderHistoryDates,
+    RequestShowOrders => ResponseShowOrders,
+    RequestShowOrderHistory => ResponseShowOrderHistory,
+    RequestShowOrderHistorySummary => ResponseShowOrderHistorySummary,
+    RequestShowOrderHistoryDetail => ResponseShowOrderHistoryDetail,
+    RequestOcoOrder => ResponseOcoOrder,
+    RequestBracketOrder => ResponseBracketOrder,
+    RequestUpdateTargetBracketLevel => ResponseUpdateTargetBracketLevel,
+    RequestUpdateStopBracketLevel => ResponseUpdateStopBracketLevel,
+    RequestSubscribeToBracketUpdates => ResponseSubscribeToBracketUpdates,
+    RequestShowBrackets => ResponseShowBrackets,
+    RequestShowBracketStops => ResponseShowBracketStops,
+    RequestListExchangePermissions => ResponseListExchangePermissions,
+    RequestLinkOrders => ResponseLinkOrders,
+    RequestCancelAllOrders => ResponseCancelAllOrders,
+    RequestEasyToBorrowList => ResponseEasyToBorrowList,
+    RequestPnLPositionUpdates => ResponsePnLPositionUpdates,
+    RequestPnLPositionSnapshot => ResponsePnLPositionSnapshot,
+    RequestListUnacceptedAgreements => ResponseListUnacceptedAgreements,
+    RequestListAcceptedAgreements => ResponseListAcceptedAgreements,
+    RequestAcceptAgreement => ResponseAcceptAgreement,
+    RequestShowAgreement => ResponseShowAgreement,
+    RequestSetRithmicMrktDataSelfCertStatus => ResponseSetRithmicMrktDataSelfCertStatus,
+    RequestModifyOrderReferenceData => ResponseModifyOrderReferenceData,
+    RequestOrderSessionConfig => ResponseOrderSessionConfig,
+    RequestExitPosition => ResponseExitPosition,
+    RequestReplayExecutions => ResponseReplayExecutions,
+    RequestAccountRmsUpdates => ResponseAccountRmsUpdates,
+}
@@ -0,0 +1,110 @@
+use std::fmt;
+
+/// The outcome reported in the `rp_code` field of a response or `Reject` (75).
+/// Element 0 is the numeric code and any following elements are text from the server, eg: `["7", "no data"]`.
+/// Only documented codes are classified, every other code is [`RithmicResponseCode::Other`] with the server text, the text is never used to guess a kind.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RithmicResponseCode {
+    /// Code "0".
+    Success,
+    /// Code "7", the request was valid but there is nothing to return, eg: a bar replay for a period without trades.
+    NoData { code: String, text: String },
+    Other { code: String, text: String },
+}
+
+impl RithmicResponseCode {
+    /// Parse an `rp_code` vector, returns None if it is empty.
+    pub fn parse(rp_code: &[String]) -> Option<RithmicResponseCode> {
+        let code = rp_code.first()?.trim().to_string();
+        let text = rp_code[1..].join(", ");
+        let response_code = match code.as_str() {
+            "0" => RithmicResponseCode::Success,
+            "7" => RithmicResponseCode::NoData { code, text },
+            _ => RithmicResponseCode::Other { code, text },
+        };
+        Some(response_code)
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(self, RithmicResponseCode::Success)
+    }
+
+    pub fn is_no_data(&self) -> bool {
+        matches!(self, RithmicResponseCode::NoData { .. })
+    }
+
+    /// The numeric code sent by the server, "0" for success.
+    pub fn code(&self) -> &str {
+        match self {
+            RithmicResponseCode::Success => "0",
+            RithmicResponseCode::NoData { code, .. }
+            | RithmicResponseCode::Other { code, .. } => code,
+        }
+    }
+
+    /// The text sent by the server, empty for success.
+    pub fn text(&self) -> &str {
+        match self {
+            RithmicResponseCode::Success => "",
+            RithmicResponseCode::NoData { text, .. }
+            | RithmicResponseCode::Other { text, .. } => text,
+        }
+    }
+}
+
+impl fmt::Display for RithmicResponseCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            RithmicResponseCode::Success => return write!(f, "success (0)"),
+            RithmicResponseCode::NoData { .. } => "no data",
+            RithmicResponseCode::Other { .. } => "error",
+        };
+        write!(f, "{} ({}): {}", kind, self.code(), self.text())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rp_code(parts: &[&str]) -> Vec<String> {
+        parts.iter().map(|part| part.to_string()).collect()
+    }
+
+    #[test]
+    fn zero_is_success() {
+        assert_eq!(RithmicResponseCode::parse(&rp_code(&["0"])), Some(RithmicResponseCode::Success));
+        assert_eq!(RithmicResponseCode::parse(&rp_code(&[" 0 "])), Some(RithmicResponseCode::Success));
+        assert!(RithmicResponseCode::Success.is_success());
+        assert_eq!(RithmicResponseCode::Success.to_string(), "success (0)");
+    }
+
+    #[test]
+    fn seven_is_no_data_whatever_the_text() {
+        let response_code = RithmicResponseCode::parse(&rp_code(&["7", "permission denied"])).unwrap();
+        assert!(response_code.is_no_data());
+        assert_eq!(response_code.code(), "7");
+        assert_eq!(response_code.text(), "permission denied");
+    }
+
+    #[test]
+    fn text_of_several_parts_is_joined() {
+        let response_code = RithmicResponseCode::parse(&rp_code(&["7", "no data", "for period"])).unwrap();
+        assert_eq!(response_code, RithmicResponseCode::NoData { code: "7".to_string(), text: "no data, for period".to_string() });
+        assert_eq!(response_code.to_string(), "no data (7): no data, for period");
+    }
+
+    #[test]
+    fn other_codes_are_not_guessed_from_the_text() {
+        for text in ["not found", "unknown symbol", "permission denied", "try again", "no data"] {
+            let response_code = RithmicResponseCode::parse(&rp_code(&["3", text])).unwrap();
+            assert_eq!(response_code, RithmicResponseCode::Other { code: "3".to_string(), text: text.to_string() });
+        }
+        assert_eq!(RithmicResponseCode::parse(&rp_code(&["1"])), Some(RithmicResponseCode::Other { code: "1".to_string(), text: String::new() }));
+    }
+
+    #[test]
+    fn empty_rp_code_is_none() {
+        assert_eq!(RithmicResponseCode::parse(&[]), None);
+    }
+}
@@ -0,0 +1,217 @@
+use std::sync::Arc;
+use std::time::Duration;
+use futures_util::stream::BoxStream;
+use futures_util::{Stream, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use crate::api_client::RithmicApiClient;
+use crate::connection::{ConnectionState, PlantConnection};
+use crate::control::ControlEvent;
+use crate::errors::RithmicApiError;
+use crate::messages::RithmicMessage;
+use crate::response_code::RithmicResponseCode;
+use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
+use crate::rithmic_proto_objects::rti::ResponseLogin;
+
+/// The details returned by the server in `ResponseLogin` (11).
+#[derive(Clone, Debug, PartialEq)]
+pub struct LoginInfo {
+    pub plant: SysInfraType,
+    pub fcm_id: Option<String>,
+    pub ib_id: Option<String>,
+    pub country_code: Option<String>,
+    pub state_code: Option<String>,
+    pub unique_user_id: Option<String>,
+    /// How often the server expects a heartbeat when nothing else is sent.
+    pub heartbeat_interval: Option<Duration>,
+}
+
+/// A plant that has been logged in to, returned by [`crate::api_client::RithmicApiClient::connect_and_login`].
+#[derive(Debug)]
+pub struct LoginSession {
+    pub stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    pub info: LoginInfo,
+}
+
+impl LoginSession {
+    /// Returns [`RithmicApiError::LoginRejected`] unless the login response has an rp_code of "0".
+    pub(crate) fn from_response(
+        plant: SysInfraType,
+        stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+        response: ResponseLogin,
+    ) -> Result<Self, RithmicApiError> {
+        match RithmicResponseCode::parse(&response.rp_code) {
+            Some(RithmicResponseCode::Success) => {}
+            Some(code) => {
+                return Err(RithmicApiError::LoginRejected {
+                    code: code.code().to_string(),
+                    text: code.text().to_string(),
+                })
+            }
+            None => {
+                return Err(RithmicApiError::LoginRejected {
+                    code: String::new(),
+                    text: "Login response has no rp_code".to_string(),
+                })
+            }
+        }
+
+        let heartbeat_interval = response
+            .heartbeat_interval
+            .filter(|seconds| seconds.is_finite() && *seconds > 0.0)
+            .map(Duration::from_secs_f64);
+
+        Ok(Self {
+            stream,
+            info: LoginInfo {
+                plant,
+                fcm_id: response.fcm_id,
+                ib_id: response.ib_id,
+                country_code: response.country_code,
+                state_code: response.state_code,
+                unique_user_id: response.unique_user_id,
+                heartbeat_interval,
+            },
+        })
+    }
+}
+
+/// Everything that happens on the plants of a [`RithmicSession`], tagged with the plant.
+// not boxed for the same reason as RithmicMessage
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Debug)]
+pub enum SessionEvent {
+    /// A message decoded from a plant.
+    Message { plant: SysInfraType, message: RithmicMessage },
+    /// A forced logout, reject or account update, see [`PlantConnection::control_events`].
+    Control(ControlEvent),
+    /// A plant changed state, eg: it is reconnecting.
+    State { plant: SysInfraType, state: ConnectionState },
+}
+
+/// A set of plants logged in to together and managed as one unit.
+pub struct RithmicSession {
+    client: Arc<RithmicApiClient>,
+    /// In the order the plants were requested.
+    connections: Vec<PlantConnection>,
+    fcm_id: Option<String>,
+    ib_id: Option<String>,
+}
+
+impl RithmicSession {
+    /// Log in to every plant at the same time.
+    /// If any login fails the plants that did log in are logged out again and the first error is returned.
+    pub async fn connect(client: Arc<RithmicApiClient>, plants: &[SysInfraType]) -> Result<RithmicSession, RithmicApiError> {
+        let mut unique_plants: Vec<SysInfraType> = Vec::with_capacity(plants.len());
+        for plant in plants {
+            if !unique_plants.contains(plant) {
+                unique_plants.push(*plant);
+            }
+        }
+
+        let results = futures_util::future::join_all(unique_plants.iter().map(|plant| client.connect_plant(*plant))).await;
+        let mut connections = Vec::with_capacity(results.len());
+        let mut error = None;
+        for result in results {
+            match result {
+                Ok(connection) => connections.push(connection),
+                Err(e) => {
+                    if error.is_none() {
+                        error = Some(e);
+                    }
+                }
+            }
+        }
+        if let Some(e) = error {
+            for connection in &connections {
+                if let Err(e) = client.shutdown_connection(connection.plant()).await {
+                    eprintln!("Failed to log out of {:?}: {}", connection.plant(), e);
+                }
+            }
+            return Err(e);
+        }
+
+        // only some plants return the fcm_id and ib_id, the first login that does is used for the whole session
+        let logins: Vec<LoginInfo> = connections.iter().map(|connection| connection.login_info()).collect();
+        let fcm_id = logins.iter().find_map(|login| login.fcm_id.clone());
+        let ib_id = logins.iter().find_map(|login| login.ib_id.clone());
+
+        Ok(RithmicSession {
+            client,
+            connections,
+            fcm_id,
+            ib_id,
+        })
+    }
+
+    pub fn client(&self) -> &Arc<RithmicApiClient> {
+        &self.client
+    }
+
+    /// The fcm_id for order and PnL requests, shared by every plant.
+    pub fn fcm_id(&self) -> Option<&str> {
+        self.fcm_id.as_deref()
+    }
+
+    /// The ib_id for order and PnL requests, shared by every plant.
+    pub fn ib_id(&self) -> Option<&str> {
+        self.ib_id.as_deref()
+    }
+
+    /// The plants of the session, in the order they were requested.
+    pub fn plants(&self) -> Vec<SysInfraType> {
+        self.connections.iter().map(|connection| connection.plant()).collect()
+    }
+
+    /// The handle for one plant, None if the session did not log in to it.
+    pub fn plant(&self, plant: SysInfraType) -> Option<&PlantConnection> {
+        self.connections.iter().find(|connection| connection.plant() == plant)
+    }
+
+    /// Returns true if every plant is logged in.
+    pub fn is_connected(&self) -> bool {
+        self.connections.iter().all(|connection| connection.is_connected())
+    }
+
+    /// Messages, control events and state changes from every plant from now on, in one stream.
+    pub fn events(&self) -> impl Stream<Item = SessionEvent> {
+        let mut streams: Vec<BoxStream<'static, SessionEvent>> = Vec::with_capacity(self.connections.len() * 3);
+        for connection in &self.connections {
+            let plant = connection.plant();
+            streams.push(connection.messages().map(move |message| SessionEvent::Message { plant, message }).boxed());
+            streams.push(futures_util::stream::unfold(connection.control_events(), |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok(event) => return Some((SessionEvent::Control(event), receiver)),
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            eprintln!("Control event stream lagged, skipped {} events", skipped);
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            }).boxed());
+            streams.push(futures_util::stream::unfold(connection.watch_state(), move |mut receiver| async move {
+                receiver.changed().await.ok()?;
+                let state = receiver.borrow_and_update().clone();
+                Some((SessionEvent::State { plant, state }, receiver))
+            }).boxed());
+        }
+        futures_util::stream::select_all(streams)
+    }
+
+    /// Log out of every plant, one after the other in the order they were requested.
+    /// Every plant is logged out even if one fails, the first error is returned.
+    pub async fn shutdown_all(&self) -> Result<(), RithmicApiError> {
+        let mut result = Ok(());
+        for connection in &self.connections {
+            if let Err(e) = self.client.shutdown_connection(connection.plant()).await {
+                eprintln!("Failed to log out of {:?}: {}", connection.plant(), e);
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+}
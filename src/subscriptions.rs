@@ -0,0 +1,301 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use crate::connection::PlantConnection;
+use crate::errors::RithmicApiError;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::{request_depth_by_order_updates, request_market_data_update, request_market_data_update_by_underlying};
+use crate::rithmic_proto_objects::rti::{RequestDepthByOrderUpdates, RequestMarketDataUpdate, RequestMarketDataUpdateByUnderlying};
+use crate::templates::TemplateId;
+
+/// What a set of update bits is subscribed for.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MarketDataKey {
+    /// One symbol, subscribed with `RequestMarketDataUpdate` (100).
+    Symbol { symbol: String, exchange: String },
+    /// Every instrument of an underlying, or of one expiration, subscribed with `RequestMarketDataUpdateByUnderlying` (105).
+    Underlying { underlying_symbol: String, exchange: String, expiration_date: Option<String> },
+    /// The orders of one symbol, subscribed with `RequestDepthByOrderUpdates` (117), which has no update bits so it is held with [`DEPTH_BY_ORDER_BIT`].
+    DepthByOrder { symbol: String, exchange: String },
+}
+
+/// The bit held for a [`MarketDataKey::DepthByOrder`] subscription.
+pub const DEPTH_BY_ORDER_BIT: u32 = 1;
+
+impl MarketDataKey {
+    pub fn symbol(symbol: &str, exchange: &str) -> Self {
+        MarketDataKey::Symbol { symbol: symbol.to_string(), exchange: exchange.to_string() }
+    }
+
+    pub fn underlying(underlying_symbol: &str, exchange: &str, expiration_date: Option<&str>) -> Self {
+        MarketDataKey::Underlying {
+            underlying_symbol: underlying_symbol.to_string(),
+            exchange: exchange.to_string(),
+            expiration_date: expiration_date.map(str::to_string),
+        }
+    }
+
+    pub fn depth_by_order(symbol: &str, exchange: &str) -> Self {
+        MarketDataKey::DepthByOrder { symbol: symbol.to_string(), exchange: exchange.to_string() }
+    }
+
+    /// The subscribe or unsubscribe request for the bits.
+    fn request(&self, subscribe: bool, update_bits: u32) -> RithmicMessage {
+        match self {
+            MarketDataKey::Symbol { symbol, exchange } => {
+                let request = match subscribe {
+                    true => request_market_data_update::Request::Subscribe,
+                    false => request_market_data_update::Request::Unsubscribe,
+                };
+                RithmicMessage::RequestMarketDataUpdate(RequestMarketDataUpdate {
+                    template_id: TemplateId::RequestMarketDataUpdate.id(),
+                    user_msg: vec![],
+                    symbol: Some(symbol.clone()),
+                    exchange: Some(exchange.clone()),
+                    request: Some(request as i32),
+                    update_bits: Some(update_bits),
+                })
+            }
+            MarketDataKey::Underlying { underlying_symbol, exchange, expiration_date } => {
+                let request = match subscribe {
+                    true => request_market_data_update_by_underlying::Request::Subscribe,
+                    false => request_market_data_update_by_underlying::Request::Unsubscribe,
+                };
+                RithmicMessage::RequestMarketDataUpdateByUnderlying(RequestMarketDataUpdateByUnderlying {
+                    template_id: TemplateId::RequestMarketDataUpdateByUnderlying.id(),
+                    user_msg: vec![],
+                    underlying_symbol: Some(underlying_symbol.clone()),
+                    exchange: Some(exchange.clone()),
+                    expiration_date: expiration_date.clone(),
+                    request: Some(request as i32),
+                    update_bits: Some(update_bits),
+                })
+            }
+            MarketDataKey::DepthByOrder { symbol, exchange } => {
+                let request = match subscribe {
+                    true => request_depth_by_order_updates::Request::Subscribe,
+                    false => request_depth_by_order_updates::Request::Unsubscribe,
+                };
+                RithmicMessage::RequestDepthByOrderUpdates(RequestDepthByOrderUpdates {
+                    template_id: TemplateId::RequestDepthByOrderUpdates.id(),
+                    user_msg: vec![],
+                    request: Some(request as i32),
+                    symbol: Some(symbol.clone()),
+                    exchange: Some(exchange.clone()),
+                    depth_price: None,
+                })
+            }
+        }
+    }
+
+    /// Send a subscribe or unsubscribe request for the bits.
+    async fn send(&self, connection: &PlantConnection, subscribe: bool, update_bits: u32) -> Result<(), RithmicApiError> {
+        connection.send_message(&self.request(subscribe, update_bits)).await
+    }
+}
+
+impl std::fmt::Display for MarketDataKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MarketDataKey::Symbol { symbol, exchange } => write!(f, "{} {}", symbol, exchange),
+            MarketDataKey::DepthByOrder { symbol, exchange } => write!(f, "{} {} depth by order", symbol, exchange),
+            MarketDataKey::Underlying { underlying_symbol, exchange, expiration_date: Some(expiration_date) } => write!(f, "{} {} {}", underlying_symbol, exchange, expiration_date),
+            MarketDataKey::Underlying { underlying_symbol, exchange, expiration_date: None } => write!(f, "{} {}", underlying_symbol, exchange),
+        }
+    }
+}
+
+#[derive(Default)]
+struct SubscriptionState {
+    connection: Option<PlantConnection>,
+    /// The number of guards holding each update bit, per key.
+    counts: HashMap<MarketDataKey, HashMap<u32, usize>>,
+}
+
+impl SubscriptionState {
+    /// The bits with at least one guard.
+    fn bits(&self, key: &MarketDataKey) -> u32 {
+        self.counts.get(key).map_or(0, |counts| counts.keys().fold(0, |bits, bit| bits | bit))
+    }
+}
+
+/// Shares `RequestMarketDataUpdate`, `RequestMarketDataUpdateByUnderlying` and `RequestDepthByOrderUpdates` subscriptions on the ticker plant between consumers.
+/// Each consumer holds a [`MarketDataGuard`] for the `UpdateBits` it needs, a bit is only subscribed by the first guard
+/// and only unsubscribed when the last guard for it is released, so consumers of the same symbol or chain do not cancel each other's data.
+/// A plant that reconnects sends its subscriptions again by itself, when a new ticker plant connection is attached every active bit is subscribed on it.
+/// Cloning gives another handle to the same subscriptions.
+#[derive(Clone, Default)]
+pub struct MarketDataSubscriptions {
+    state: Arc<Mutex<SubscriptionState>>,
+}
+
+impl MarketDataSubscriptions {
+    /// Use a new ticker plant connection and subscribe every active bit on it.
+    pub async fn attach(&self, connection: PlantConnection) -> Result<(), RithmicApiError> {
+        let mut state = self.state.lock().await;
+        state.connection = Some(connection.clone());
+        let mut result = Ok(());
+        for key in state.counts.keys() {
+            if let Err(e) = key.send(&connection, true, state.bits(key)).await {
+                eprintln!("Failed to subscribe to market data for {}: {}", key, e);
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    /// The ticker plant connection the subscriptions are sent on.
+    pub async fn connection(&self) -> Option<PlantConnection> {
+        self.state.lock().await.connection.clone()
+    }
+
+    /// Hold the `UpdateBits` for a symbol, the bits that no other guard holds are subscribed.
+    /// If the subscribe request can not be sent no bit is held, `update_bits` of 0 is rejected.
+    pub async fn subscribe(&self, symbol: &str, exchange: &str, update_bits: u32) -> Result<MarketDataGuard, RithmicApiError> {
+        self.subscribe_key(MarketDataKey::symbol(symbol, exchange), update_bits).await
+    }
+
+    /// Hold the `UpdateBits` for every instrument of an underlying, or of one expiration of it, eg: an options chain.
+    /// The instruments are subscribed and unsubscribed as a group.
+    pub async fn subscribe_underlying(&self, underlying_symbol: &str, exchange: &str, expiration_date: Option<&str>, update_bits: u32) -> Result<MarketDataGuard, RithmicApiError> {
+        self.subscribe_key(MarketDataKey::underlying(underlying_symbol, exchange, expiration_date), update_bits).await
+    }
+
+    /// Hold the depth by order updates of a symbol, they are only unsubscribed when the last guard is released.
+    pub async fn subscribe_depth_by_order(&self, symbol: &str, exchange: &str) -> Result<MarketDataGuard, RithmicApiError> {
+        self.subscribe_key(MarketDataKey::depth_by_order(symbol, exchange), DEPTH_BY_ORDER_BIT).await
+    }
+
+    async fn subscribe_key(&self, key: MarketDataKey, update_bits: u32) -> Result<MarketDataGuard, RithmicApiError> {
+        if update_bits == 0 {
+            return Err(RithmicApiError::ClientErrorDebug(format!("No update bits to subscribe for {}", key)))
+        }
+        let mut state = self.state.lock().await;
+        let connection = match &state.connection {
+            Some(connection) => connection.clone(),
+            None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+        };
+        let new_bits = update_bits & !state.bits(&key);
+        if new_bits != 0 {
+            key.send(&connection, true, new_bits).await?;
+        }
+        let counts = state.counts.entry(key.clone()).or_default();
+        for bit in split_bits(update_bits) {
+            *counts.entry(bit).or_default() += 1;
+        }
+        Ok(MarketDataGuard {
+            subscriptions: self.clone(),
+            key,
+            update_bits,
+            released: false,
+        })
+    }
+
+    /// Send the subscribe again for the bits of a symbol that are held, eg: for a new snapshot after messages were missed.
+    /// Only bits that a guard holds are sent and no bit is held by this, so releasing the guards still unsubscribes.
+    pub async fn resubscribe(&self, symbol: &str, exchange: &str, update_bits: u32) -> Result<(), RithmicApiError> {
+        let key = MarketDataKey::symbol(symbol, exchange);
+        let state = self.state.lock().await;
+        let held_bits = update_bits & state.bits(&key);
+        match (&state.connection, held_bits) {
+            (Some(connection), bits) if bits != 0 => key.send(connection, true, bits).await,
+            (None, _) => Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// The bits currently held for a symbol or underlying.
+    pub async fn update_bits(&self, key: &MarketDataKey) -> u32 {
+        self.state.lock().await.bits(key)
+    }
+
+    /// Every symbol and underlying with the bits held for it.
+    pub async fn active(&self) -> Vec<(MarketDataKey, u32)> {
+        let state = self.state.lock().await;
+        state.counts.keys().map(|key| (key.clone(), state.bits(key))).collect()
+    }
+
+    async fn release(&self, key: &MarketDataKey, update_bits: u32) -> Result<(), RithmicApiError> {
+        let mut state = self.state.lock().await;
+        let mut removed_bits = 0;
+        if let Some(counts) = state.counts.get_mut(key) {
+            for bit in split_bits(update_bits) {
+                if let Some(count) = counts.get_mut(&bit) {
+                    *count -= 1;
+                    if *count == 0 {
+                        counts.remove(&bit);
+                        removed_bits |= bit;
+                    }
+                }
+            }
+            if counts.is_empty() {
+                state.counts.remove(key);
+            }
+        }
+        match (&state.connection, removed_bits) {
+            (Some(connection), bits) if bits != 0 => {
+                let result = key.send(connection, false, bits).await;
+                if result.is_err() {
+                    // no guard holds the bits anymore, they must not be sent again after a reconnect
+                    connection.forget_subscription(key.request(false, bits));
+                }
+                result
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Holds market data update bits for a symbol or underlying, see [`MarketDataSubscriptions::subscribe`].
+/// Dropping the guard releases the bits in a background task, use [`MarketDataGuard::release`] to wait for the unsubscribe.
+pub struct MarketDataGuard {
+    subscriptions: MarketDataSubscriptions,
+    key: MarketDataKey,
+    update_bits: u32,
+    released: bool,
+}
+
+impl MarketDataGuard {
+    pub fn key(&self) -> &MarketDataKey {
+        &self.key
+    }
+
+    pub fn update_bits(&self) -> u32 {
+        self.update_bits
+    }
+
+    /// Release the bits, unsubscribing those no other guard holds.
+    /// The bits are released even if the unsubscribe can not be sent, eg: while reconnecting, they are not subscribed again after the reconnect.
+    pub async fn release(mut self) -> Result<(), RithmicApiError> {
+        self.released = true;
+        self.subscriptions.release(&self.key, self.update_bits).await
+    }
+}
+
+impl Drop for MarketDataGuard {
+    fn drop(&mut self) {
+        if self.released {
+            return;
+        }
+        let subscriptions = self.subscriptions.clone();
+        let key = self.key.clone();
+        let update_bits = self.update_bits;
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(e) = subscriptions.release(&key, update_bits).await {
+                        eprintln!("Failed to unsubscribe from market data for {}: {}", key, e);
+                    }
+                });
+            }
+            Err(_) => eprintln!("Market data guard for {} dropped outside a tokio runtime, the bits stay subscribed", key),
+        }
+    }
+}
+
+/// Each set bit on its own, eg: 0b101 gives 0b001 and 0b100.
+fn split_bits(update_bits: u32) -> impl Iterator<Item = u32> {
+    (0..u32::BITS).map(|shift| 1 << shift).filter(move |bit| update_bits & bit != 0)
+}
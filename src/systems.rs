@@ -1,10 +1,13 @@
-use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use rkyv::{Archive, Deserialize as Deserialize_rkyv, Serialize as Serialize_rkyv};
-use strum_macros::Display;
 use crate::errors::RithmicApiError;
 
-#[derive(Serialize, Deserialize, Clone, Eq, Serialize_rkyv, Deserialize_rkyv,
-    Archive, PartialEq, Debug, Hash, PartialOrd, Ord, Display, Copy)]
+/// A system (prop firm or rithmic environment) as named by the server in `ResponseRithmicSystemInfo`.
+/// Serialized as the server name, eg: "Rithmic Test", systems this crate does not know are kept as `Other`.
+#[derive(Clone, Eq, Serialize_rkyv, Deserialize_rkyv,
+    Archive, PartialEq, Debug, Hash, PartialOrd, Ord)]
 #[archive(compare(PartialEq), check_bytes)]
 #[archive_attr(derive(Debug))]
 pub enum RithmicSystem {
@@ -24,6 +27,8 @@ pub enum RithmicSystem {
     PropShopTrader,
     FourPropTrader,
     FastTrackTrading,
+    /// A system advertised by the server that has no variant yet, holds the exact server name.
+    Other(String),
 }
 
 #[allow(dead_code)]
@@ -44,30 +49,31 @@ impl RithmicSystem {
 }
 
 impl RithmicSystem {
-    /// Converts the enum variant to its corresponding string representation.
-    pub fn to_string(&self) -> String {
+    /// The name the server uses for the system, `to_string()` returns the same.
+    pub fn name(&self) -> &str {
         match self {
-            RithmicSystem::Rithmic01 => "Rithmic 01".to_string(),
-            RithmicSystem::Rithmic04Colo => "Rithmic 04 Colo".to_string(),
-            RithmicSystem::RithmicPaperTrading => "Rithmic Paper Trading".to_string(),
-            RithmicSystem::RithmicTest => "Rithmic Test".to_string(),
-            RithmicSystem::TopstepTrader => "TopstepTrader".to_string(),
-            RithmicSystem::SpeedUp => "SpeedUp".to_string(),
-            RithmicSystem::TradeFundrr => "TradeFundrr".to_string(),
-            RithmicSystem::UProfitTrader => "UProfitTrader".to_string(),
-            RithmicSystem::Apex => "Apex".to_string(),
-            RithmicSystem::MESCapital => "MES Capital".to_string(),
-            RithmicSystem::TheTradingPit => "The Trading Pit".to_string(),
-            RithmicSystem::FundedFuturesNetwork => "Funded Futures Network".to_string(),
-            RithmicSystem::Bulenox => "Bulenox".to_string(),
-            RithmicSystem::PropShopTrader => "PropShopTrader".to_string(),
-            RithmicSystem::FourPropTrader => "4PropTrader".to_string(),
-            RithmicSystem::FastTrackTrading => "FastTrackTrading".to_string(),
+            RithmicSystem::Rithmic01 => "Rithmic 01",
+            RithmicSystem::Rithmic04Colo => "Rithmic 04 Colo",
+            RithmicSystem::RithmicPaperTrading => "Rithmic Paper Trading",
+            RithmicSystem::RithmicTest => "Rithmic Test",
+            RithmicSystem::TopstepTrader => "TopstepTrader",
+            RithmicSystem::SpeedUp => "SpeedUp",
+            RithmicSystem::TradeFundrr => "TradeFundrr",
+            RithmicSystem::UProfitTrader => "UProfitTrader",
+            RithmicSystem::Apex => "Apex",
+            RithmicSystem::MESCapital => "MES Capital",
+            RithmicSystem::TheTradingPit => "The Trading Pit",
+            RithmicSystem::FundedFuturesNetwork => "Funded Futures Network",
+            RithmicSystem::Bulenox => "Bulenox",
+            RithmicSystem::PropShopTrader => "PropShopTrader",
+            RithmicSystem::FourPropTrader => "4PropTrader",
+            RithmicSystem::FastTrackTrading => "FastTrackTrading",
+            RithmicSystem::Other(name) => name,
         }
     }
 
-    /// Converts a string into the corresponding enum variant.
-    /// Returns None if the string doesn't match any variant.
+    /// Converts a server name into the corresponding enum variant.
+    /// Returns None if the string doesn't match any known variant, use `RithmicSystem::from` to keep unknown names as `Other`.
     pub fn from_string(s: &str) -> Option<RithmicSystem> {
         match s {
             "Rithmic 01" => Some(RithmicSystem::Rithmic01),
@@ -90,6 +96,29 @@ impl RithmicSystem {
         }
     }
 
+    /// Converts a variant name, eg: "RithmicTest", as written by older versions of this crate.
+    fn from_variant_name(s: &str) -> Option<RithmicSystem> {
+        match s {
+            "Rithmic01" => Some(RithmicSystem::Rithmic01),
+            "Rithmic04Colo" => Some(RithmicSystem::Rithmic04Colo),
+            "RithmicPaperTrading" => Some(RithmicSystem::RithmicPaperTrading),
+            "RithmicTest" => Some(RithmicSystem::RithmicTest),
+            "TopstepTrader" => Some(RithmicSystem::TopstepTrader),
+            "SpeedUp" => Some(RithmicSystem::SpeedUp),
+            "TradeFundrr" => Some(RithmicSystem::TradeFundrr),
+            "UProfitTrader" => Some(RithmicSystem::UProfitTrader),
+            "Apex" => Some(RithmicSystem::Apex),
+            "MESCapital" => Some(RithmicSystem::MESCapital),
+            "TheTradingPit" => Some(RithmicSystem::TheTradingPit),
+            "FundedFuturesNetwork" => Some(RithmicSystem::FundedFuturesNetwork),
+            "Bulenox" => Some(RithmicSystem::Bulenox),
+            "PropShopTrader" => Some(RithmicSystem::PropShopTrader),
+            "FourPropTrader" => Some(RithmicSystem::FourPropTrader),
+            "FastTrackTrading" => Some(RithmicSystem::FastTrackTrading),
+            _ => None,
+        }
+    }
+
     pub fn file_string(&self) -> String {
         match self {
             RithmicSystem::Rithmic01 => "rithmic_01.toml".to_string(),
@@ -108,9 +137,17 @@ impl RithmicSystem {
             RithmicSystem::PropShopTrader => "propshop_trader.toml".to_string(),
             RithmicSystem::FourPropTrader => "4prop_trader.toml".to_string(),
             RithmicSystem::FastTrackTrading => "fasttrack_trading.toml".to_string(),
+            RithmicSystem::Other(name) => {
+                let name: String = name
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '_' })
+                    .collect();
+                format!("{}.toml", name)
+            }
         }
     }
 
+    /// Returns None for file names of `Other` systems, since the original server name can not be recovered.
     pub fn from_file_string(file_name: &str) -> Option<Self> {
         match file_name {
             "rithmic_01.toml" => Some(RithmicSystem::Rithmic01),
@@ -133,3 +170,53 @@ impl RithmicSystem {
         }
     }
 }
+
+impl fmt::Display for RithmicSystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl From<&str> for RithmicSystem {
+    /// Accepts the server name or the variant name, anything else becomes `Other`.
+    fn from(s: &str) -> Self {
+        RithmicSystem::from_string(s)
+            .or_else(|| RithmicSystem::from_variant_name(s))
+            .unwrap_or_else(|| RithmicSystem::Other(s.to_string()))
+    }
+}
+
+impl From<String> for RithmicSystem {
+    fn from(s: String) -> Self {
+        RithmicSystem::from(s.as_str())
+    }
+}
+
+impl FromStr for RithmicSystem {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(RithmicSystem::from(s))
+    }
+}
+
+impl Serialize for RithmicSystem {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+impl<'de> Deserialize<'de> for RithmicSystem {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = <String as Deserialize>::deserialize(deserializer)?;
+        Ok(RithmicSystem::from(name))
+    }
+}
+
+/// A system listed in `ResponseRithmicSystemInfo` (17).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RithmicSystemInfo {
+    pub system: RithmicSystem,
+    /// True if the system can send aggregated quotes, see `RithmicCredentials::aggregated_quotes`.
+    pub has_aggregated_quotes: bool,
+}
@@ -0,0 +1,129 @@
+use std::fmt;
+use crate::errors::RithmicApiError;
+use crate::rithmic_proto_objects::rti::request_login::SysInfraType;
+
+/// Generated together with [`crate::messages::RithmicMessage`] from the table in `messages.rs`, so every template has a message type.
+pub use crate::messages::TemplateId;
+
+/// Every plant, used for messages that can be sent to or received from any infrastructure plant.
+pub const ALL_PLANTS: [SysInfraType; 5] = [
+    SysInfraType::TickerPlant,
+    SysInfraType::OrderPlant,
+    SysInfraType::HistoryPlant,
+    SysInfraType::PnlPlant,
+    SysInfraType::RepositoryPlant,
+];
+
+/// Which side of the connection originates a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum MessageDirection {
+    /// Requests we send to rithmic.
+    FromClient,
+    /// Responses and updates sent to us by rithmic.
+    FromServer,
+}
+
+impl TemplateId {
+    /// The raw template_id used on the wire.
+    pub fn id(&self) -> i32 {
+        *self as i32
+    }
+
+    /// The plants that accept or send this template.
+    /// System info, gateway info, heartbeats, login and the control messages (Reject, UserAccountUpdate, ForcedLogout) are shared by all plants,
+    /// reference data (14, 15) is only served by the ticker plant.
+    pub fn plants(&self) -> &'static [SysInfraType] {
+        match self.id() {
+            // reference data is in the shared range but only served by the ticker plant
+            14 | 15 => &[SysInfraType::TickerPlant],
+            10..=99 => &ALL_PLANTS,
+            100..=199 => &[SysInfraType::TickerPlant],
+            200..=299 => &[SysInfraType::HistoryPlant],
+            300..=399 | 3500..=3599 => &[SysInfraType::OrderPlant],
+            400..=499 => &[SysInfraType::PnlPlant],
+            500..=599 => &[SysInfraType::RepositoryPlant],
+            _ => &[],
+        }
+    }
+
+    /// Returns true if the plant accepts (or sends) this template.
+    pub fn is_accepted_by(&self, plant: SysInfraType) -> bool {
+        self.plants().contains(&plant)
+    }
+
+    /// Returns an error if this template should not be sent to or received from the plant.
+    pub fn validate_route(&self, plant: SysInfraType) -> Result<(), RithmicApiError> {
+        match self.is_accepted_by(plant) {
+            true => Ok(()),
+            false => Err(RithmicApiError::ClientErrorDebug(format!("{:?} (Template ID: {}) is not accepted by {:?}", self, self.id(), plant)))
+        }
+    }
+
+    /// Whether the template is sent by the client or the server, every template sent by the client is a request.
+    pub fn direction(&self) -> MessageDirection {
+        match self.message_type().starts_with("Request") {
+            true => MessageDirection::FromClient,
+            false => MessageDirection::FromServer,
+        }
+    }
+}
+
+impl TryFrom<i32> for TemplateId {
+    type Error = RithmicApiError;
+
+    fn try_from(template_id: i32) -> Result<Self, Self::Error> {
+        TemplateId::from_i32(template_id).ok_or(RithmicApiError::UnknownTemplateId(template_id))
+    }
+}
+
+impl fmt::Display for TemplateId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} (Template ID: {})", self, self.id())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use prost::Message;
+    use super::*;
+    use crate::messages::decode_message;
+    use crate::rithmic_proto_objects::rti::MessageType;
+
+    #[test]
+    fn every_template_round_trips_through_from_i32() {
+        for template in TemplateId::ALL {
+            assert_eq!(TemplateId::from_i32(template.id()), Some(*template));
+            assert_eq!(TemplateId::try_from(template.id()).ok(), Some(*template));
+        }
+        assert_eq!(TemplateId::from_i32(0), None);
+        assert_eq!(TemplateId::from_i32(9999), None);
+    }
+
+    #[test]
+    fn every_template_has_a_message_variant() {
+        for template in TemplateId::ALL {
+            // every field but the template_id is optional, so the bare header decodes as any type
+            let bytes = MessageType { template_id: template.id() }.encode_to_vec();
+            let message = decode_message(&bytes).unwrap();
+            assert_eq!(message.template(), *template);
+            assert_eq!(message.name(), template.message_type());
+        }
+    }
+
+    #[test]
+    fn responses_and_requests_agree() {
+        for template in TemplateId::ALL {
+            for response in template.responses() {
+                assert_eq!(template.direction(), MessageDirection::FromClient);
+                assert_eq!(response.direction(), MessageDirection::FromServer);
+                assert_eq!(response.request(), Some(*template));
+            }
+            if let Some(request) = template.request() {
+                assert!(request.responses().contains(template));
+            }
+        }
+        assert_eq!(TemplateId::RequestGetInstrumentByUnderlying.responses(), &[TemplateId::ResponseGetInstrumentByUnderlying, TemplateId::ResponseGetInstrumentByUnderlyingKeys]);
+        assert_eq!(TemplateId::LastTrade.request(), None);
+        assert!(TemplateId::LastTrade.responses().is_empty());
+    }
+}
@@ -0,0 +1,71 @@
+use std::future::Future;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{client_async, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use crate::errors::RithmicApiError;
+
+/// Upper bounds for each network operation, an operation that takes longer fails with [`RithmicApiError::Timeout`].
+#[derive(Clone, Debug)]
+pub struct Timeouts {
+    /// Opening the TCP connection and completing the TLS handshake.
+    pub connect: Duration,
+    /// Upgrading the connection to a WebSocket.
+    pub handshake: Duration,
+    /// Sending a request before login and receiving its response, eg: `RequestLogin` or `RequestRithmicSystemInfo`.
+    pub login: Duration,
+    /// Waiting for the final response to a request sent with `request()`.
+    pub request: Duration,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            connect: Duration::from_secs(10),
+            handshake: Duration::from_secs(10),
+            login: Duration::from_secs(30),
+            request: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Await the future for at most `duration`, the future is dropped if it takes longer.
+pub(crate) async fn with_timeout<F: Future>(duration: Duration, operation: impl FnOnce() -> String, future: F) -> Result<F::Output, RithmicApiError> {
+    match tokio::time::timeout(duration, future).await {
+        Ok(output) => Ok(output),
+        Err(_) => Err(RithmicApiError::Timeout { operation: operation() }),
+    }
+}
+
+/// Open a WebSocket to a `ws://` or `wss://` uri, with separate timeouts for the TCP and TLS connect and the WebSocket handshake.
+/// Cancel safe, if the future is dropped the partly opened connection is dropped with it.
+pub(crate) async fn connect_websocket(uri: &str, timeouts: &Timeouts) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, RithmicApiError> {
+    let request = uri.into_client_request()?;
+    let host = match request.uri().host() {
+        Some(host) => host.to_string(),
+        None => return Err(RithmicApiError::InvalidConfig(format!("No host in uri: {}", uri)))
+    };
+    let tls = match request.uri().scheme_str() {
+        Some("wss") => true,
+        Some("ws") => false,
+        _ => return Err(RithmicApiError::InvalidConfig(format!("Uri must start with ws:// or wss://: {}", uri)))
+    };
+    let port = request.uri().port_u16().unwrap_or(if tls { 443 } else { 80 });
+
+    let stream = with_timeout(timeouts.connect, || format!("connect to {}:{}", host, port), async {
+        let tcp_stream = TcpStream::connect((host.as_str(), port)).await?;
+        if !tls {
+            return Ok(MaybeTlsStream::Plain(tcp_stream))
+        }
+        let connector = native_tls::TlsConnector::new()
+            .map_err(|e| RithmicApiError::ClientErrorDebug(format!("Failed to create TLS connector: {}", e)))?;
+        let tls_stream = tokio_native_tls::TlsConnector::from(connector)
+            .connect(&host, tcp_stream)
+            .await
+            .map_err(|e| RithmicApiError::ServerErrorDebug(format!("TLS handshake with {} failed: {}", host, e)))?;
+        Ok::<_, RithmicApiError>(MaybeTlsStream::NativeTls(tls_stream))
+    }).await??;
+
+    let (stream, _) = with_timeout(timeouts.handshake, || format!("WebSocket handshake with {}", host), client_async(request, stream)).await??;
+    Ok(stream)
+}
@@ -0,0 +1,373 @@
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+use crate::connection::{ConnectionState, PlantConnection};
+use crate::errors::RithmicApiError;
+use crate::market_by_order::Price;
+use crate::messages::RithmicMessage;
+use crate::rithmic_proto_objects::rti::last_trade::PresenceBits;
+use crate::rithmic_proto_objects::rti::request_market_data_update::UpdateBits;
+use crate::rithmic_proto_objects::rti::{LastTrade, RequestGetVolumeAtPrice, ResponseGetVolumeAtPrice};
+use crate::subscriptions::{MarketDataGuard, MarketDataSubscriptions};
+use crate::templates::TemplateId;
+
+/// The share of the total volume in the value area when none is given.
+pub const DEFAULT_VALUE_AREA: f64 = 0.7;
+
+/// The price range around the point of control that holds a share of the total volume.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ValueArea {
+    pub low: f64,
+    pub high: f64,
+    pub volume: u64,
+}
+
+/// The volume traded at each price of a symbol, built from `ResponseGetVolumeAtPrice` (120).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct VolumeProfile {
+    pub symbol: String,
+    pub exchange: String,
+    levels: BTreeMap<Price, u64>,
+    total_volume: u64,
+    /// The time of the response as (ssboe, usecs), trades before it are already counted.
+    as_of: Option<(i32, i32)>,
+}
+
+impl VolumeProfile {
+    pub fn new(symbol: String, exchange: String) -> Self {
+        Self {
+            symbol,
+            exchange,
+            ..Self::default()
+        }
+    }
+
+    /// Zip the prices and volumes of every row of the response.
+    pub fn from_responses(symbol: String, exchange: String, responses: &[ResponseGetVolumeAtPrice]) -> Self {
+        let mut profile = VolumeProfile::new(symbol, exchange);
+        for response in responses {
+            for (price, volume) in response.trade_price.iter().zip(response.volume_at_price.iter()) {
+                profile.add_volume(*price, (*volume).max(0) as u64);
+            }
+            if let Some(ssboe) = response.ssboe {
+                profile.as_of = profile.as_of.max(Some((ssboe, response.usecs.unwrap_or_default())));
+            }
+        }
+        profile
+    }
+
+    pub fn add_volume(&mut self, price: f64, volume: u64) {
+        *self.levels.entry(Price(price)).or_default() += volume;
+        self.total_volume += volume;
+    }
+
+    /// Add the size of a trade print, returns false if the message is not a new trade for this symbol or is older than the response.
+    pub fn apply_trade(&mut self, trade: &LastTrade) -> bool {
+        if trade.symbol.as_deref() != Some(self.symbol.as_str()) || trade.exchange.as_deref().is_some_and(|exchange| exchange != self.exchange) {
+            return false;
+        }
+        // the snapshot sent on subscribe repeats a trade that is already counted
+        if trade.is_snapshot == Some(true) || trade.presence_bits.unwrap_or_default() & PresenceBits::LastTrade as u32 == 0 {
+            return false;
+        }
+        let (price, size) = match (trade.trade_price, trade.trade_size) {
+            (Some(price), Some(size)) if size > 0 => (price, size as u64),
+            _ => return false,
+        };
+        // a sweep through several levels sends several prints with the same time, so only trades before the response are skipped
+        if let (Some(ssboe), Some(as_of)) = (trade.ssboe, self.as_of) {
+            if (ssboe, trade.usecs.unwrap_or_default()) < as_of {
+                return false;
+            }
+        }
+        self.add_volume(price, size);
+        true
+    }
+
+    pub fn total_volume(&self) -> u64 {
+        self.total_volume
+    }
+
+    pub fn volume_at(&self, price: f64) -> u64 {
+        self.levels.get(&Price(price)).copied().unwrap_or_default()
+    }
+
+    /// Every price with its volume, lowest price first.
+    pub fn levels(&self) -> impl Iterator<Item = (f64, u64)> + '_ {
+        self.levels.iter().map(|(price, volume)| (price.0, *volume))
+    }
+
+    /// The price with the most volume and its volume, the lowest such price if several have the same volume.
+    pub fn point_of_control(&self) -> Option<(f64, u64)> {
+        self.levels()
+            .fold(None, |best: Option<(f64, u64)>, (price, volume)| match best {
+                Some((_, best_volume)) if best_volume >= volume => best,
+                _ => Some((price, volume)),
+            })
+    }
+
+    /// The smallest range around the point of control holding `share` of the total volume, eg: 0.7 for 70%.
+    /// Starting at the point of control, the next price above or below with the most volume is added until the share is reached.
+    pub fn value_area(&self, share: f64) -> Option<ValueArea> {
+        let levels: Vec<(f64, u64)> = self.levels().collect();
+        let (poc_price, poc_volume) = self.point_of_control()?;
+        let target = (self.total_volume as f64 * share.clamp(0.0, 1.0)).ceil() as u64;
+        let poc = levels.iter().position(|(price, _)| *price == poc_price)?;
+        let (mut low, mut high, mut volume) = (poc, poc, poc_volume);
+        while volume < target {
+            let below = low.checked_sub(1).map(|i| levels[i].1);
+            let above = levels.get(high + 1).map(|level| level.1);
+            match (below, above) {
+                (Some(below), Some(above)) if above >= below => {
+                    high += 1;
+                    volume += above;
+                }
+                (Some(below), _) => {
+                    low -= 1;
+                    volume += below;
+                }
+                (None, Some(above)) => {
+                    high += 1;
+                    volume += above;
+                }
+                (None, None) => break,
+            }
+        }
+        Some(ValueArea { low: levels[low].0, high: levels[high].0, volume })
+    }
+}
+
+/// A volume profile kept current by a background task, see [`track_volume_profile`].
+/// Dropping the handle stops the task and releases the `LAST_TRADE` subscription.
+pub struct VolumeProfileHandle {
+    profile: Arc<RwLock<VolumeProfile>>,
+    total_volume: watch::Receiver<u64>,
+    /// Taken by `stop()`, otherwise released when the handle is dropped.
+    guard: Option<MarketDataGuard>,
+    task: JoinHandle<()>,
+}
+
+impl VolumeProfileHandle {
+    /// Read the profile, the lock is held while `read` runs so keep it short.
+    pub fn with_profile<R>(&self, read: impl FnOnce(&VolumeProfile) -> R) -> R {
+        read(&self.profile.read().unwrap())
+    }
+
+    /// Changes with every trade added and every time the profile is requested again.
+    pub fn watch_total_volume(&self) -> watch::Receiver<u64> {
+        self.total_volume.clone()
+    }
+
+    /// Stop the task and wait for the `LAST_TRADE` subscription to be released.
+    pub async fn stop(mut self) -> Result<(), RithmicApiError> {
+        self.task.abort();
+        match self.guard.take() {
+            Some(guard) => guard.release().await,
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for VolumeProfileHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Request the volume at price of a symbol on the ticker plant and keep it current with its `LastTrade` prints.
+/// Trades older than the response are skipped, and the profile is requested again after a reconnect since trades were missed.
+pub async fn track_volume_profile(subscriptions: &MarketDataSubscriptions, symbol: String, exchange: String, timeout: Duration) -> Result<VolumeProfileHandle, RithmicApiError> {
+    let connection = match subscriptions.connection().await {
+        Some(connection) => connection,
+        None => return Err(RithmicApiError::ClientErrorDebug("No connection for plant: TickerPlant".to_string()))
+    };
+    // trades received while waiting for the response queue up here and are filtered by time afterwards
+    let inbound = connection.subscribe();
+    let guard = subscriptions.subscribe(&symbol, &exchange, UpdateBits::LastTrade as u32).await?;
+    let profile = request_volume_profile(&connection, &symbol, &exchange, timeout).await?;
+    let (total_volume_sender, total_volume) = watch::channel(profile.total_volume());
+    let profile = Arc::new(RwLock::new(profile));
+    let task = tokio::task::spawn(run_volume_profile(connection, profile.clone(), inbound, total_volume_sender, timeout));
+    Ok(VolumeProfileHandle { profile, total_volume, guard: Some(guard), task })
+}
+
+async fn request_volume_profile(connection: &PlantConnection, symbol: &str, exchange: &str, timeout: Duration) -> Result<VolumeProfile, RithmicApiError> {
+    let request = RequestGetVolumeAtPrice {
+        template_id: TemplateId::RequestGetVolumeAtPrice.id(),
+        user_msg: vec![],
+        symbol: Some(symbol.to_string()),
+        exchange: Some(exchange.to_string()),
+    };
+    let responses = connection.request(request, timeout).await?;
+    Ok(VolumeProfile::from_responses(symbol.to_string(), exchange.to_string(), &responses))
+}
+
+async fn run_volume_profile(
+    connection: PlantConnection,
+    profile: Arc<RwLock<VolumeProfile>>,
+    mut inbound: broadcast::Receiver<RithmicMessage>,
+    total_volume: watch::Sender<u64>,
+    timeout: Duration,
+) {
+    let (symbol, exchange) = {
+        let profile = profile.read().unwrap();
+        (profile.symbol.clone(), profile.exchange.clone())
+    };
+    let mut state = connection.watch_state();
+    let mut reconnecting = false;
+    loop {
+        tokio::select! {
+            message = inbound.recv() => {
+                match message {
+                    Ok(RithmicMessage::LastTrade(trade)) => {
+                        let mut profile = profile.write().unwrap();
+                        if profile.apply_trade(&trade) {
+                            total_volume.send_replace(profile.total_volume());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        eprintln!("Volume profile for {} {} lagged, skipped {} messages", symbol, exchange, skipped);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+            changed = state.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                let current = state.borrow_and_update().clone();
+                match current {
+                    ConnectionState::Reconnecting { .. } => reconnecting = true,
+                    ConnectionState::Connected if reconnecting => {
+                        reconnecting = false;
+                        // trades received meanwhile queue up in the receiver and are filtered by time afterwards
+                        match request_volume_profile(&connection, &symbol, &exchange, timeout).await {
+                            Ok(new_profile) => {
+                                total_volume.send_replace(new_profile.total_volume());
+                                *profile.write().unwrap() = new_profile;
+                            }
+                            Err(e) => eprintln!("Failed to request the volume profile for {} {}: {}", symbol, exchange, e),
+                        }
+                    }
+                    _ if current.is_terminal() => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(levels: &[(f64, u64)]) -> VolumeProfile {
+        let mut profile = VolumeProfile::new("ESZ4".to_string(), "CME".to_string());
+        for (price, volume) in levels {
+            profile.add_volume(*price, *volume);
+        }
+        profile
+    }
+
+    fn response(ssboe: i32, usecs: i32) -> ResponseGetVolumeAtPrice {
+        ResponseGetVolumeAtPrice {
+            template_id: TemplateId::ResponseGetVolumeAtPrice.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            trade_price: vec![100.0, 100.25],
+            volume_at_price: vec![10, 20],
+            ssboe: Some(ssboe),
+            usecs: Some(usecs),
+            ..Default::default()
+        }
+    }
+
+    fn trade(price: f64, size: i32, ssboe: i32, usecs: i32) -> LastTrade {
+        LastTrade {
+            template_id: TemplateId::LastTrade.id(),
+            symbol: Some("ESZ4".to_string()),
+            exchange: Some("CME".to_string()),
+            presence_bits: Some(PresenceBits::LastTrade as u32),
+            trade_price: Some(price),
+            trade_size: Some(size),
+            ssboe: Some(ssboe),
+            usecs: Some(usecs),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn from_responses_sums_the_rows() {
+        let profile = VolumeProfile::from_responses("ESZ4".to_string(), "CME".to_string(), &[response(1000, 0), response(1000, 5)]);
+        assert_eq!(profile.total_volume(), 60);
+        assert_eq!(profile.volume_at(100.0), 20);
+        assert_eq!(profile.volume_at(100.25), 40);
+        assert_eq!(profile.as_of, Some((1000, 5)));
+    }
+
+    #[test]
+    fn trades_with_the_same_time_are_all_counted() {
+        let mut profile = VolumeProfile::from_responses("ESZ4".to_string(), "CME".to_string(), &[response(1000, 500)]);
+        // a sweep through two levels at the time of the response
+        assert!(profile.apply_trade(&trade(100.0, 2, 1000, 500)));
+        assert!(profile.apply_trade(&trade(100.25, 3, 1000, 500)));
+        assert!(profile.apply_trade(&trade(100.25, 1, 1000, 500)));
+        assert_eq!(profile.volume_at(100.0), 12);
+        assert_eq!(profile.volume_at(100.25), 24);
+        assert_eq!(profile.total_volume(), 36);
+    }
+
+    #[test]
+    fn trades_before_the_response_are_skipped() {
+        let mut profile = VolumeProfile::from_responses("ESZ4".to_string(), "CME".to_string(), &[response(1000, 500)]);
+        assert!(!profile.apply_trade(&trade(100.0, 2, 1000, 499)));
+        assert!(!profile.apply_trade(&trade(100.0, 2, 999, 900)));
+        assert!(profile.apply_trade(&trade(100.0, 2, 1001, 0)));
+        assert_eq!(profile.total_volume(), 32);
+    }
+
+    #[test]
+    fn apply_trade_skips_other_symbols_and_snapshots() {
+        let mut profile = profile(&[]);
+        let mut other = trade(100.0, 1, 1, 0);
+        other.symbol = Some("NQZ4".to_string());
+        assert!(!profile.apply_trade(&other));
+        let mut other_exchange = trade(100.0, 1, 1, 0);
+        other_exchange.exchange = Some("CBOT".to_string());
+        assert!(!profile.apply_trade(&other_exchange));
+        let mut snapshot = trade(100.0, 1, 1, 0);
+        snapshot.is_snapshot = Some(true);
+        assert!(!profile.apply_trade(&snapshot));
+        let mut no_trade = trade(100.0, 1, 1, 0);
+        no_trade.presence_bits = Some(0);
+        assert!(!profile.apply_trade(&no_trade));
+        assert!(!profile.apply_trade(&trade(100.0, 0, 1, 0)));
+        assert_eq!(profile.total_volume(), 0);
+    }
+
+    #[test]
+    fn point_of_control_prefers_the_lowest_price_on_a_tie() {
+        assert_eq!(profile(&[]).point_of_control(), None);
+        let profile = profile(&[(100.0, 5), (100.25, 9), (100.5, 9), (100.75, 1)]);
+        assert_eq!(profile.point_of_control(), Some((100.25, 9)));
+    }
+
+    #[test]
+    fn value_area_grows_towards_the_larger_side() {
+        let profile = profile(&[(100.0, 10), (101.0, 20), (102.0, 50), (103.0, 15), (104.0, 5)]);
+        assert_eq!(profile.value_area(DEFAULT_VALUE_AREA), Some(ValueArea { low: 101.0, high: 102.0, volume: 70 }));
+        assert_eq!(profile.value_area(0.85), Some(ValueArea { low: 101.0, high: 103.0, volume: 85 }));
+        assert_eq!(profile.value_area(1.0), Some(ValueArea { low: 100.0, high: 104.0, volume: 100 }));
+        assert_eq!(profile.value_area(0.0), Some(ValueArea { low: 102.0, high: 102.0, volume: 50 }));
+    }
+
+    #[test]
+    fn value_area_prefers_above_on_a_tie() {
+        let profile = profile(&[(100.0, 10), (101.0, 30), (102.0, 10)]);
+        assert_eq!(profile.value_area(0.8), Some(ValueArea { low: 101.0, high: 102.0, volume: 40 }));
+        assert_eq!(VolumeProfile::default().value_area(0.7), None);
+    }
+}